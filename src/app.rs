@@ -1,11 +1,87 @@
+mod animation;
+mod audio;
+mod bow;
+mod dispersion;
 mod display;
+mod export;
+mod expression;
+mod figure;
+mod frequency;
+mod hammer;
+pub mod headless;
+mod heatmap;
+mod history;
+mod kymograph;
+mod obstacle;
+mod phase;
+mod preset;
+mod probe;
+mod raster;
+mod resample;
+mod scenario;
+mod shapes;
 mod string_dynamics;
+mod transform;
+mod undo;
 
-use self::display::DisplaySettings;
+use self::animation::{Animation, AnimationFormat};
+use self::audio::{wav, Pickup, PickupQuantity};
+use self::bow::BowHistory;
+use self::dispersion::{dispersion, space_time_spectrum};
+use self::display::{DisplaySettings, PlotTool};
+use self::export::{load_file, save_file};
+use self::expression::NamedExpression;
+use self::figure::{string_points, PlotExport, PlotFormat};
+use self::frequency::{estimate_peaks, spectrogram, Peak};
+use self::heatmap::{heat_image, Colormap, HeatmapTexture};
+use self::history::History;
+use self::kymograph::Kymograph;
+use self::obstacle::*;
+use self::phase::{PhaseKind, PhasePortrait, SectionSource};
+use self::preset::{built_in, Preset};
+use self::probe::{Probes, Quantity};
+use self::resample::Resampling;
+use self::scenario::{Scenario, DELTA_RANGE};
+use self::shapes::{Pulse, PulseKind, Travel};
 use self::string_dynamics::*;
+use self::transform::{peaks, sine_transform};
+use self::undo::UndoStack;
 use eframe::egui;
 use eframe::egui::{plot::*, Ui};
 use eframe::epaint::Color32;
+use std::f64::consts::PI;
+
+/// Probe, quantity, sample count, time of the latest sample and time-step
+/// that a peak estimate was made from.
+type PeakKey = (usize, Quantity, usize, f64, f64);
+
+/// The state of the simulation and its settings that undo and redo restore.
+/// The saved expressions and presets are libraries rather than simulation
+/// state, so they are left out.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    time: f64,
+    system: Sys,
+    size: usize,
+    resampling: Resampling,
+    insert_inertia: f64,
+    initial_displacement: f64,
+    clamped: bool,
+    max_time: f64,
+    delta: f64,
+    display_settings: DisplaySettings,
+    expression: NamedExpression,
+    modes: Vec<Mode>,
+    pulse: Pulse,
+}
+
+impl Default for Snapshot {
+    fn default() -> Self {
+        SystemPlot::default().snapshot()
+    }
+}
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
@@ -15,11 +91,80 @@ pub struct SystemPlot {
     time: f64,
     system: string_dynamics::Sys,
     size: usize,
+    resampling: Resampling,
+    insert_inertia: f64,
     initial_displacement: f64,
     clamped: bool,
     max_time: f64,
     delta: f64,
     display_settings: DisplaySettings,
+    expression: NamedExpression,
+    saved_expressions: Vec<NamedExpression>,
+    modes: Vec<Mode>,
+    pulse: Pulse,
+    presets: Vec<Preset>,
+    probes: Probes,
+    analysis_probe: usize,
+    spectrogram_window: usize,
+    live_peaks: bool,
+    phase: PhasePortrait,
+    kymograph: Kymograph,
+    history: History,
+    pickup: Pickup,
+    animation: Animation,
+    plot_export: PlotExport,
+    reversal_steps: usize,
+    undo_stack: UndoStack<Snapshot>,
+    /// Whether the history is being played backwards.
+    #[serde(skip)]
+    rewinding: bool,
+    /// Whether animation integrates backward in time.
+    #[serde(skip)]
+    reverse: bool,
+    #[serde(skip)]
+    reversal_error: Option<f64>,
+    #[serde(skip)]
+    kymograph_texture: HeatmapTexture,
+    #[serde(skip)]
+    dispersion_texture: HeatmapTexture,
+    /// Number of modes and frequency bins of the computed dispersion image.
+    #[serde(skip)]
+    dispersion_extent: Option<(usize, usize)>,
+    #[serde(skip)]
+    spectrogram_texture: HeatmapTexture,
+    #[serde(skip)]
+    peaks: Option<(PeakKey, Vec<Peak>)>,
+    /// The string before the number of masses started changing, and the
+    /// latest string resampled from it.
+    #[serde(skip)]
+    resample_origin: Option<(Sys, Sys)>,
+    #[serde(skip)]
+    bow_history: BowHistory,
+    #[serde(skip)]
+    probe_mass: usize,
+    #[serde(skip)]
+    export_path: String,
+    #[serde(skip)]
+    audio_path: String,
+    #[serde(skip)]
+    animation_path: String,
+    #[serde(skip)]
+    plot_path: String,
+    #[serde(skip)]
+    scenario_path: String,
+    #[serde(skip)]
+    preset_name: String,
+    /// Index of the saved preset being renamed and the name typed so far.
+    #[serde(skip)]
+    preset_rename: Option<(usize, String)>,
+    #[serde(skip)]
+    built_in_presets: Vec<Preset>,
+    #[serde(skip)]
+    export_status: String,
+    #[serde(skip)]
+    dragged: Option<usize>,
+    #[serde(skip)]
+    stroke: Vec<[f64; 2]>,
 }
 
 impl Default for SystemPlot {
@@ -29,11 +174,52 @@ impl Default for SystemPlot {
             time: 0.0,
             system: Sys::new(0, 10, 1.0),
             size: 10,
+            resampling: Resampling::Linear,
+            insert_inertia: 1.0,
             initial_displacement: 1.0,
             clamped: false,
             max_time: 100.0,
             delta: 0.1,
             display_settings: Default::default(),
+            expression: Default::default(),
+            saved_expressions: Vec::new(),
+            modes: vec![Default::default()],
+            pulse: Default::default(),
+            presets: Vec::new(),
+            probes: Default::default(),
+            analysis_probe: 0,
+            spectrogram_window: 256,
+            live_peaks: true,
+            phase: Default::default(),
+            kymograph: Default::default(),
+            history: Default::default(),
+            pickup: Default::default(),
+            animation: Default::default(),
+            plot_export: Default::default(),
+            reversal_steps: 1000,
+            undo_stack: Default::default(),
+            rewinding: false,
+            reverse: false,
+            reversal_error: None,
+            kymograph_texture: Default::default(),
+            dispersion_texture: Default::default(),
+            dispersion_extent: None,
+            spectrogram_texture: Default::default(),
+            peaks: None,
+            resample_origin: None,
+            bow_history: Default::default(),
+            probe_mass: 0,
+            export_path: "probes.csv".to_string(),
+            audio_path: "string.wav".to_string(),
+            animation_path: "string.gif".to_string(),
+            plot_path: "string.svg".to_string(),
+            scenario_path: "scenario.ron".to_string(),
+            preset_name: "My preset".to_string(),
+            preset_rename: None,
+            built_in_presets: built_in(),
+            export_status: String::new(),
+            dragged: None,
+            stroke: Vec::new(),
         }
     }
 }
@@ -55,17 +241,9 @@ impl SystemPlot {
     }
 
     fn line_points(&self, width: f64) -> Line {
-        let n = self.size;
-        let points = (0..n + 2).map(|i| {
-            if i == 0 || i == n + 1 {
-                Value::new(((i as f64) / ((n + 1) as f64)) * width, 0.0)
-            } else {
-                Value::new(
-                    ((i as f64) / ((n + 1) as f64)) * width,
-                    self.system.get_mass_pos(i - 1),
-                )
-            }
-        });
+        let points = string_points(&self.system, width)
+            .into_iter()
+            .map(|[x, y]| Value::new(x, y));
 
         Line::new(Values::from_values_iter(points))
             .style(LineStyle::Solid)
@@ -73,13 +251,10 @@ impl SystemPlot {
     }
 
     fn circle_points(&self, radius: f32, width: f64) -> Points {
-        let n = self.size;
-        let circle = (0..n).map(|i| {
-            Value::new(
-                ((i as f64 + 1.0) / ((n + 1) as f64)) * width,
-                self.system.get_mass_pos(i),
-            )
-        });
+        let points = string_points(&self.system, width);
+        let circle = points[1..points.len() - 1]
+            .iter()
+            .map(|&[x, y]| Value::new(x, y));
         Points::new(Values::from_values_iter(circle))
             .name("mass")
             .filled(true)
@@ -87,15 +262,502 @@ impl SystemPlot {
             .shape(MarkerShape::Circle)
     }
 
+    /// Advances the simulation by one time-step and records the probes and
+    /// the history.
+    fn step(&mut self) {
+        self.history
+            .before_step(&self.system, self.time, self.delta);
+        self.system.update_system(&mut self.time, self.delta);
+        self.history.after_step(&self.system, self.time);
+        self.probes.record(&self.system, self.time, self.delta);
+        self.bow_history.record(&self.system, self.time, self.delta);
+        if self.display_settings.show_phase {
+            self.phase.record(&self.system, self.delta);
+        }
+        if self.display_settings.show_kymograph {
+            self.kymograph.record(&self.system, self.time);
+        }
+    }
+
+    /// Index of the mass drawn under the pointer, if any.
+    fn mass_under_pointer(&self, plot_ui: &PlotUi) -> Option<usize> {
+        let pointer = plot_ui.screen_from_plot(plot_ui.pointer_coordinate()?);
+        let n = self.size;
+        (0..n)
+            .map(|i| {
+                let center = plot_ui.screen_from_plot(Value::new(
+                    ((i as f64 + 1.0 + self.system.get_mass_long_pos(i)) / ((n + 1) as f64))
+                        * self.display_settings.width,
+                    self.system.get_mass_pos(i),
+                ));
+                (i, center.distance(pointer))
+            })
+            .filter(|&(_, distance)| distance <= self.display_settings.radius.max(4.0))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(i, _)| i)
+    }
+
+    /// Loads the freehand stroke onto the masses, with the string pinned to
+    /// zero at both boundaries.
+    fn apply_stroke(&mut self) {
+        let width = self.display_settings.width;
+        let points: Vec<[f64; 2]> = self.stroke.iter().map(|&[x, y]| [x / width, y]).collect();
+        // A click, or a stroke drawn outside the string, has no shape to load.
+        let shape = match shapes::stroke(&points, self.size) {
+            Some(shape) => shape,
+            None => return,
+        };
+        self.animate = false;
+        self.system.reset(0, self.size, 0.0);
+        self.time = 0.0;
+        self.system.set_shape(&shape);
+    }
+
+    /// Mode amplitudes of the current shape, with the largest peaks labelled.
+    fn spectrum(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Spatial spectrum");
+            ui.checkbox(&mut self.display_settings.spectrum_log, "Log scale");
+        });
+        let amplitudes: Vec<f64> = sine_transform(&self.system.positions())
+            .iter()
+            .map(|b| b.abs())
+            .collect();
+        let log = self.display_settings.spectrum_log;
+        let floor = if log {
+            let largest = amplitudes.iter().copied().fold(f64::MIN_POSITIVE, f64::max);
+            largest.log10() - 8.0
+        } else {
+            0.0
+        };
+        let height = |b: f64| {
+            if log {
+                b.max(f64::MIN_POSITIVE).log10().max(floor)
+            } else {
+                b
+            }
+        };
+        let bars = amplitudes
+            .iter()
+            .enumerate()
+            .map(|(k, &b)| {
+                Bar::new((k + 1) as f64, height(b) - floor)
+                    .base_offset(floor)
+                    .width(0.8)
+            })
+            .collect();
+        let color = self.display_settings.display_colors[1];
+        Plot::new("Spectrum")
+            .include_y(floor)
+            .label_formatter(move |_, value| {
+                if log {
+                    format!("mode {:.0}\n{:.3e}", value.x, 10f64.powf(value.y))
+                } else {
+                    format!("mode {:.0}\n{:.4}", value.x, value.y)
+                }
+            })
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new(bars).color(color).name("amplitude"));
+                for k in peaks(&amplitudes, 5, 1e-3) {
+                    plot_ui.text(
+                        Text::new(
+                            Value::new((k + 1) as f64, height(amplitudes[k])),
+                            format!("{}", k + 1),
+                        )
+                        .anchor(egui::Align2::CENTER_BOTTOM)
+                        .color(color),
+                    );
+                }
+            });
+    }
+
+    /// Time series of every probe for the selected quantity.
+    fn probe_plot(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Probes");
+            egui::ComboBox::from_id_source("probe quantity")
+                .selected_text(self.probes.quantity.to_string())
+                .show_ui(ui, |ui| {
+                    for quantity in [
+                        Quantity::Displacement,
+                        Quantity::Velocity,
+                        Quantity::Acceleration,
+                    ] {
+                        ui.selectable_value(
+                            &mut self.probes.quantity,
+                            quantity,
+                            quantity.to_string(),
+                        );
+                    }
+                });
+        });
+        let quantity = self.probes.quantity;
+        Plot::new("Probes")
+            .legend(Legend::default())
+            .show(ui, |plot_ui| {
+                for probe in &self.probes.probes {
+                    plot_ui.line(
+                        Line::new(Values::from_values_iter(
+                            probe
+                                .samples()
+                                .iter()
+                                .map(|sample| Value::new(sample.time, sample.get(quantity))),
+                        ))
+                        .color(probe.color)
+                        .name(format!("mass {}", probe.mass)),
+                    );
+                }
+            });
+    }
+
+    /// Samples of the probe selected for frequency analysis.
+    fn analysis_samples(&self) -> Vec<f64> {
+        let quantity = self.probes.quantity;
+        self.probes
+            .probes
+            .get(self.analysis_probe)
+            .map(|probe| probe.samples().iter().map(|s| s.get(quantity)).collect())
+            .unwrap_or_default()
+    }
+
+    fn peak_key(&self) -> PeakKey {
+        let samples = self.probes.probes[self.analysis_probe].samples();
+        (
+            self.analysis_probe,
+            self.probes.quantity,
+            samples.len(),
+            samples.back().map_or(0.0, |s| s.time),
+            self.delta,
+        )
+    }
+
+    /// Mode whose theoretical frequency is closest to `frequency`.
+    fn nearest_mode(&self, frequency: f64) -> usize {
+        (1..=self.size)
+            .min_by(|&a, &b| {
+                let da = (self.system.eigenfrequency(a) - frequency).abs();
+                let db = (self.system.eigenfrequency(b) - frequency).abs();
+                da.partial_cmp(&db).unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap_or(1)
+    }
+
+    /// Short-time spectrum of the analysed probe, with the theoretical
+    /// eigenfrequencies of the chain drawn on top.
+    fn spectrogram_plot(&mut self, ui: &mut Ui) {
+        ui.label("Spectrogram (angular frequency against time)");
+        let samples = self.analysis_samples();
+        let window = self.spectrogram_window;
+        // One texture column per spectrum, so their number is limited by the GPU.
+        let max_columns = ui.ctx().input().max_texture_side;
+        let (hop, columns) = spectrogram(&samples, window, max_columns);
+        let columns: Vec<Vec<f64>> = columns
+            .into_iter()
+            .map(|column| column.iter().map(|m| 20.0 * (m + 1e-12).log10()).collect())
+            .collect();
+        if columns.is_empty() {
+            ui.label(format!("Needs at least {} samples.", window));
+            return;
+        }
+        // Keep 60 dB of dynamic range.
+        let loudest = columns.iter().flatten().copied().fold(f64::MIN, f64::max);
+        let columns: Vec<Vec<f64>> = columns
+            .into_iter()
+            .map(|c| c.into_iter().map(|v| v.max(loudest - 60.0)).collect())
+            .collect();
+        let image = heat_image(&columns, Colormap::Viridis, max_columns);
+        let texture = self
+            .spectrogram_texture
+            .update(ui.ctx(), "spectrogram", image);
+        let start = self.probes.probes[self.analysis_probe]
+            .samples()
+            .front()
+            .map_or(0.0, |s| s.time);
+        let hop = hop as f64 * self.delta;
+        let duration = columns.len() as f64 * hop;
+        let nyquist = PI / self.delta;
+        let first = start + 0.5 * window as f64 * self.delta;
+        Plot::new("Spectrogram").show(ui, |plot_ui| {
+            plot_ui.image(PlotImage::new(
+                texture,
+                Value::new(first + 0.5 * (duration - hop), 0.5 * nyquist),
+                [duration as f32, nyquist as f32],
+            ));
+            for k in (1..=self.size.min(20)).filter(|&k| self.system.eigenfrequency(k) < nyquist) {
+                plot_ui.hline(
+                    HLine::new(self.system.eigenfrequency(k))
+                        .color(self.display_settings.display_colors[0])
+                        .name("eigenfrequency"),
+                );
+            }
+        });
+    }
+
+    /// Phase portrait trail, with the Poincaré section on top.
+    fn phase_plot(&mut self, ui: &mut Ui) {
+        let (x_label, y_label) = self.phase.labels();
+        ui.label(format!("Phase space: {} against {}", y_label, x_label));
+        Plot::new("Phase space")
+            .legend(Legend::default())
+            .show(ui, |plot_ui| {
+                plot_ui.line(
+                    Line::new(Values::from_values_iter(
+                        self.phase.trail().iter().map(|&[x, y]| Value::new(x, y)),
+                    ))
+                    .color(self.display_settings.display_colors[1])
+                    .name("trajectory"),
+                );
+                if self.phase.poincare {
+                    plot_ui.points(
+                        Points::new(Values::from_values_iter(
+                            self.phase.section().iter().map(|&[x, y]| Value::new(x, y)),
+                        ))
+                        .color(self.display_settings.display_colors[0])
+                        .radius(2.0)
+                        .name("Poincaré section"),
+                    );
+                }
+            });
+    }
+
+    /// Heat map of the displacement of every mass over the recorded history.
+    fn kymograph_plot(&mut self, ui: &mut Ui) {
+        ui.label("Space-time (mass index against time)");
+        let window = self.kymograph.window();
+        let (first, last) = match (window.first(), window.last()) {
+            (Some(first), Some(last)) => (first.0, last.0),
+            _ => {
+                ui.label("Run the simulation to record the history.");
+                return;
+            }
+        };
+        let n = window[0].1.len();
+        let columns: Vec<Vec<f64>> = (0..n)
+            .map(|i| window.iter().map(|(_, row)| row[i]).collect())
+            .collect();
+        let rows = window.len();
+        let max_side = ui.ctx().input().max_texture_side;
+        let image = heat_image(&columns, self.kymograph.colormap, max_side);
+        let texture = self.kymograph_texture.update(ui.ctx(), "kymograph", image);
+        // Each row covers one time-step around its recorded time.
+        let step = if rows > 1 {
+            (last - first) / (rows - 1) as f64
+        } else {
+            self.delta
+        };
+        Plot::new("Kymograph").show(ui, |plot_ui| {
+            plot_ui.image(PlotImage::new(
+                texture,
+                Value::new(0.5 * (n as f64 - 1.0), 0.5 * (first + last)),
+                [n as f32, (last - first + step) as f32],
+            ));
+        });
+    }
+
+    /// Computes the (wavenumber, frequency) power of the space-time history
+    /// and uploads it as a texture.
+    fn compute_dispersion(&mut self, ctx: &egui::Context) {
+        let max_side = ctx.input().max_texture_side;
+        let columns = space_time_spectrum(&self.kymograph.shapes(), max_side);
+        let bins = columns.first().map_or(0, |c| c.len());
+        // Keep 80 dB of dynamic range.
+        let loudest = columns.iter().flatten().copied().fold(f64::MIN, f64::max);
+        let columns: Vec<Vec<f64>> = columns
+            .into_iter()
+            .map(|c| c.into_iter().map(|v| v.max(loudest - 80.0)).collect())
+            .collect();
+        self.dispersion_texture.update(
+            ctx,
+            "dispersion",
+            heat_image(&columns, self.kymograph.colormap, max_side),
+        );
+        self.dispersion_extent = Some((columns.len(), bins));
+    }
+
+    /// Space-time power with the theoretical dispersion curve of the chain.
+    fn dispersion_plot(&mut self, ui: &mut Ui) {
+        ui.label("Dispersion (angular frequency against wavenumber per mass spacing)");
+        let (modes, bins) = match self.dispersion_extent {
+            Some(extent) => extent,
+            None => {
+                ui.label("Record a space-time history and press \"Compute\".");
+                return;
+            }
+        };
+        let texture = self.dispersion_texture.id();
+        let spacing = PI / (modes + 1) as f64;
+        let nyquist = PI / self.delta;
+        let resolution = nyquist / (bins - 1).max(1) as f64;
+        let tension = self.system.tension();
+        Plot::new("Dispersion")
+            .legend(Legend::default())
+            .show(ui, |plot_ui| {
+                plot_ui.image(PlotImage::new(
+                    texture,
+                    Value::new(0.5 * (modes + 1) as f64 * spacing, 0.5 * nyquist),
+                    [
+                        (modes as f64 * spacing) as f32,
+                        (bins as f64 * resolution) as f32,
+                    ],
+                ));
+                plot_ui.line(
+                    Line::new(Values::from_explicit_callback(
+                        move |q| dispersion(q, tension),
+                        0.0..=PI,
+                        256,
+                    ))
+                    .color(self.display_settings.display_colors[0])
+                    .name("theory"),
+                );
+            });
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            time: self.time,
+            system: self.system.clone(),
+            size: self.size,
+            resampling: self.resampling,
+            insert_inertia: self.insert_inertia,
+            initial_displacement: self.initial_displacement,
+            clamped: self.clamped,
+            max_time: self.max_time,
+            delta: self.delta,
+            display_settings: self.display_settings.clone(),
+            expression: self.expression.clone(),
+            modes: self.modes.clone(),
+            pulse: self.pulse.clone(),
+        }
+    }
+
+    /// Whether the current state equals `snapshot`, without copying it.
+    fn matches(&self, snapshot: &Snapshot) -> bool {
+        let Snapshot {
+            time,
+            system,
+            size,
+            resampling,
+            insert_inertia,
+            initial_displacement,
+            clamped,
+            max_time,
+            delta,
+            display_settings,
+            expression,
+            modes,
+            pulse,
+        } = snapshot;
+        self.time == *time
+            && self.system == *system
+            && self.size == *size
+            && self.resampling == *resampling
+            && self.insert_inertia == *insert_inertia
+            && self.initial_displacement == *initial_displacement
+            && self.clamped == *clamped
+            && self.max_time == *max_time
+            && self.delta == *delta
+            && self.display_settings == *display_settings
+            && self.expression == *expression
+            && self.modes == *modes
+            && self.pulse == *pulse
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.animate = false;
+        self.rewinding = false;
+        self.time = snapshot.time;
+        self.system = snapshot.system;
+        self.size = snapshot.size;
+        self.resampling = snapshot.resampling;
+        self.insert_inertia = snapshot.insert_inertia;
+        self.initial_displacement = snapshot.initial_displacement;
+        self.clamped = snapshot.clamped;
+        self.max_time = snapshot.max_time;
+        self.delta = snapshot.delta;
+        self.display_settings = snapshot.display_settings;
+        self.expression = snapshot.expression;
+        self.modes = snapshot.modes;
+        self.pulse = snapshot.pulse;
+    }
+
+    fn scenario(&self) -> Scenario {
+        Scenario {
+            time: self.time,
+            delta: self.delta,
+            clamped: self.clamped,
+            max_time: self.max_time,
+            resampling: self.resampling,
+            system: self.system.clone(),
+            display: self.display_settings.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn load_scenario(&mut self, scenario: Scenario) {
+        self.animate = false;
+        self.rewinding = false;
+        self.time = scenario.time;
+        self.delta = scenario.delta;
+        self.clamped = scenario.clamped;
+        self.max_time = scenario.max_time;
+        self.resampling = scenario.resampling;
+        self.size = scenario.system.len();
+        self.system = scenario.system;
+        self.display_settings = scenario.display;
+    }
+
+    /// Undoes the last edit, or redoes the last undone one.
+    fn undo(&mut self, redo: bool) {
+        let current = self.snapshot();
+        let snapshot = if redo {
+            self.undo_stack.redo(current)
+        } else {
+            self.undo_stack.undo(current)
+        };
+        if let Some(snapshot) = snapshot {
+            self.restore(snapshot);
+        }
+        // Restoring is not an edit itself.
+        self.skip_edit();
+    }
+
+    /// Starts the frame's edit from the current state, so the changes made so
+    /// far in this frame are not recorded. Moving through time by stepping or
+    /// with the history is not an edit, like running the animation.
+    fn skip_edit(&mut self) {
+        self.undo_stack.begin_frame(self.snapshot());
+    }
+
+    /// Integrates the simulation one time-step backward.
+    fn step_back(&mut self) {
+        if self.time <= 0.5 * self.delta {
+            return;
+        }
+        self.system.step_back(&mut self.time, self.delta);
+        self.history.after_step_back(&self.system, self.time);
+    }
+
+    /// Shows the state of the history at `step`.
+    fn seek(&mut self, step: usize) {
+        if let Some((system, time)) = self.history.seek(step) {
+            self.system = system;
+            self.time = time;
+        }
+    }
+
     fn display(&mut self, ui: &mut Ui) {
         ui.heading("Side Panel");
         ui.label(format!("Time: {:.1}", self.time));
+        if !self.export_status.is_empty() {
+            ui.label(&self.export_status);
+        }
         egui::CollapsingHeader::new("Basic Settings").show(ui, |ui| {
             if ui.button("Reset").clicked() {
                 self.animate = false;
-                self.system = Sys::new(0, 10, 1.0);
+                self.system.reset(0, 10, 1.0);
                 self.size = 10;
                 self.time = 0.0;
+                self.probes.clear();
             }
             ui.add(egui::Slider::new(&mut self.size, 1..=500).text("Masses"));
             ui.horizontal(|ui| {
@@ -106,30 +768,261 @@ impl SystemPlot {
                     self.size -= 1;
                 }
             });
+            egui::ComboBox::from_label("Resampling")
+                .selected_text(self.resampling.to_string())
+                .show_ui(ui, |ui| {
+                    for method in [Resampling::Linear, Resampling::Cubic, Resampling::Spectral] {
+                        ui.selectable_value(&mut self.resampling, method, method.to_string());
+                    }
+                });
             let time_response = ui.add(
                 egui::DragValue::new(&mut self.delta)
-                    .clamp_range(0.001f64..=0.750f64)
+                    .clamp_range(DELTA_RANGE)
                     .speed(0.001)
                     .prefix("Delta: "),
             );
             if time_response.changed() {
                 self.animate = false;
-                self.system = Sys::new(0, self.size, 1.0);
+                self.system.reset(0, self.size, 1.0);
                 self.time = 0.0;
+                self.probes.clear();
+            }
+
+            ui.horizontal(|ui| {
+                if ui.checkbox(&mut self.animate, "Animate").changed() {
+                    self.rewinding = false;
+                }
+                ui.checkbox(&mut self.reverse, "Reverse");
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Step back").clicked() {
+                    self.animate = false;
+                    self.step_back();
+                    self.skip_edit();
+                }
+                if ui.button("Step").clicked() {
+                    self.animate = false;
+                    self.step();
+                    self.skip_edit();
+                }
+            });
+        });
+
+        egui::CollapsingHeader::new("Presets").show(ui, |ui| {
+            let mut loaded = None;
+            ui.label("Built-in:");
+            for preset in &self.built_in_presets {
+                ui.horizontal(|ui| {
+                    if ui.button("Load").clicked() {
+                        loaded = Some(preset.clone());
+                    }
+                    ui.label(&preset.name)
+                        .on_hover_text(&preset.scenario.description);
+                });
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut self.preset_name);
+            });
+            if ui.button("Save preset").clicked() {
+                let preset = Preset {
+                    name: self.preset_name.clone(),
+                    scenario: self.scenario(),
+                };
+                match self
+                    .presets
+                    .iter_mut()
+                    .find(|saved| saved.name == preset.name)
+                {
+                    Some(saved) => *saved = preset,
+                    None => self.presets.push(preset),
+                }
+            }
+            let mut removed = None;
+            let mut renaming = self.preset_rename.take();
+            let mut renamed = None;
+            for (i, saved) in self.presets.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.button("Load").clicked() {
+                        loaded = Some(saved.clone());
+                    }
+                    if ui.button("Delete").clicked() {
+                        removed = Some(i);
+                    }
+                    let mut name = match &renaming {
+                        Some((j, name)) if *j == i => name.clone(),
+                        _ => saved.name.clone(),
+                    };
+                    let response = ui
+                        .text_edit_singleline(&mut name)
+                        .on_hover_text("Rename, applied when the field loses focus");
+                    if response.changed() {
+                        renaming = Some((i, name));
+                    }
+                    if response.lost_focus() {
+                        renamed = Some(i);
+                    }
+                });
+            }
+            // A new name only replaces the old one once it is complete, and two
+            // presets may not share a name, since saving replaces by name.
+            if let Some(i) = renamed {
+                if let Some((j, name)) = renaming.take() {
+                    if j != i {
+                        renaming = Some((j, name));
+                    } else if name.is_empty() {
+                        self.export_status = "A preset needs a name".to_string();
+                    } else if self
+                        .presets
+                        .iter()
+                        .enumerate()
+                        .any(|(k, preset)| k != i && preset.name == name)
+                    {
+                        self.export_status = format!("A preset named {} already exists", name);
+                    } else {
+                        self.presets[i].name = name;
+                    }
+                }
+            }
+            self.preset_rename = renaming;
+            if let Some(i) = removed {
+                self.presets.remove(i);
+                self.preset_rename = None;
+            }
+            if let Some(preset) = loaded {
+                self.load_scenario(preset.scenario);
+                self.export_status = format!("Loaded preset {}", preset.name);
+            }
+        });
+
+        egui::CollapsingHeader::new("Time Reversal").show(ui, |ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.reversal_steps)
+                    .clamp_range(1..=100_000)
+                    .prefix("Steps: "),
+            );
+            if ui.button("Run test").clicked() {
+                self.reversal_error = Some(self.system.reversal_error(
+                    self.time,
+                    self.delta,
+                    self.reversal_steps,
+                ));
+            }
+            if let Some(error) = self.reversal_error {
+                ui.label(format!("Largest deviation from the start: {:.3e}", error));
             }
+        });
 
-            ui.checkbox(&mut self.animate, "Animate");
-            if ui.button("Step").clicked() {
+        egui::CollapsingHeader::new("History").show(ui, |ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.history.interval)
+                    .clamp_range(1..=1000)
+                    .prefix("Steps between keyframes: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.history.capacity)
+                    .clamp_range(1..=10000)
+                    .prefix("Keyframes kept: "),
+            );
+            if self.history.is_empty() {
+                ui.label("Run the simulation to record its history.");
+                return;
+            }
+            let (start, end) = self.history.range();
+            let mut step = self.history.cursor().unwrap_or(end);
+            ui.label(format!(
+                "Times {:.1} to {:.1}",
+                self.history.time_at(start),
+                self.history.time_at(end)
+            ));
+            if ui
+                .add(egui::Slider::new(&mut step, start..=end).text("Step"))
+                .changed()
+            {
                 self.animate = false;
-                self.system.update_system(&mut self.time, self.delta);
+                self.rewinding = false;
+                self.seek(step);
+                self.skip_edit();
+            }
+            ui.horizontal(|ui| {
+                if ui.checkbox(&mut self.rewinding, "Play backwards").changed() {
+                    self.animate = false;
+                }
+                if ui.button("Latest").clicked() {
+                    self.animate = false;
+                    self.rewinding = false;
+                    self.seek(end);
+                    self.skip_edit();
+                }
+                if ui.button("Clear").clicked() {
+                    self.rewinding = false;
+                    self.history.clear();
+                }
+            });
+            if step < end {
+                ui.label("Animating or stepping continues from here and drops the later history.");
             }
         });
 
-        while self.size > self.system.len() {
-            self.system.push(0.0);
-        }
-        while self.size < self.system.len() {
-            self.system.pop();
+        egui::CollapsingHeader::new("Spring Settings").show(ui, |ui| {
+            egui::ComboBox::from_label("Spring model")
+                .selected_text(self.system.spring_model.to_string())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut self.system.spring_model,
+                        SpringModel::Transverse,
+                        SpringModel::Transverse.to_string(),
+                    );
+                    ui.selectable_value(
+                        &mut self.system.spring_model,
+                        SpringModel::Exact,
+                        SpringModel::Exact.to_string(),
+                    );
+                });
+            ui.add_enabled(
+                self.system.spring_model == SpringModel::Transverse,
+                egui::DragValue::new(&mut self.system.nonlinearity)
+                    .speed(0.01)
+                    .clamp_range(0.0f64..=100.0f64)
+                    .prefix("Nonlinearity: "),
+            );
+            ui.add_enabled(
+                self.system.spring_model == SpringModel::Exact,
+                egui::DragValue::new(&mut self.system.stiffness)
+                    .speed(0.01)
+                    .clamp_range(0.01f64..=100.0f64)
+                    .prefix("Stiffness: "),
+            );
+            ui.add_enabled(
+                self.system.spring_model == SpringModel::Exact,
+                egui::DragValue::new(&mut self.system.rest_length)
+                    .speed(0.01)
+                    .clamp_range(0.0f64..=2.0f64)
+                    .prefix("Rest length: "),
+            );
+            ui.label(format!("Tension: {:.2}", self.system.tension()));
+            ui.add(
+                egui::DragValue::new(&mut self.system.gravity)
+                    .speed(0.001)
+                    .clamp_range(-1.0f64..=1.0f64)
+                    .prefix("Gravity: "),
+            );
+        });
+
+        if self.size != self.system.len() {
+            // Always resample from the string as it was before the number of masses
+            // started changing, so dragging the slider or pressing Increment and
+            // Decrement repeatedly does not pile up interpolation errors.
+            let origin = match self.resample_origin.take() {
+                Some((origin, latest)) if latest == self.system => origin,
+                _ => self.system.clone(),
+            };
+            self.system = origin.clone();
+            if self.size != origin.len() {
+                self.system.resample(self.size, self.resampling);
+            }
+            self.resample_origin = Some((origin, self.system.clone()));
         }
         egui::CollapsingHeader::new("Display Settings").show(ui, |ui| {
             ui.add(
@@ -177,6 +1070,20 @@ impl SystemPlot {
                     );
                 });
 
+            egui::ComboBox::from_label("Plot tool")
+                .selected_text(self.display_settings.plot_tool.to_string())
+                .show_ui(ui, |ui| {
+                    for tool in [PlotTool::Pin, PlotTool::Drag, PlotTool::Draw] {
+                        ui.selectable_value(
+                            &mut self.display_settings.plot_tool,
+                            tool,
+                            tool.to_string(),
+                        );
+                    }
+                });
+
+            ui.checkbox(&mut self.display_settings.show_spectrum, "Show spectrum");
+
             if ui.button("Windowed").clicked() {
                 self.display_settings.windowed = !self.display_settings.windowed;
             }
@@ -196,7 +1103,7 @@ impl SystemPlot {
             );
             if harmonic_response.changed() {
                 self.animate = false;
-                self.system = Sys::new(0, self.size, 0.0);
+                self.system.reset(0, self.size, 0.0);
                 self.time = 0.0;
                 self.system.harmonic_state(
                     self.initial_displacement,
@@ -206,7 +1113,7 @@ impl SystemPlot {
             ui.separator();
             if ui.button("Harmonic").clicked() {
                 self.animate = false;
-                self.system = Sys::new(0, self.size, 0.0);
+                self.system.reset(0, self.size, 0.0);
                 self.time = 0.0;
                 self.system.harmonic_state(
                     self.initial_displacement,
@@ -215,18 +1122,810 @@ impl SystemPlot {
             }
             if ui.button("Parabolic").clicked() {
                 self.animate = false;
-                self.system = Sys::new(0, self.size, 0.0);
+                self.system.reset(0, self.size, 0.0);
                 self.time = 0.0;
                 self.system.parabola(self.initial_displacement);
             }
             if ui.button("Pluck").clicked() {
                 self.animate = false;
-                self.system = Sys::new(0, self.size, 0.0);
+                self.system.reset(0, self.size, 0.0);
+                self.time = 0.0;
+                self.system.pluck(self.initial_displacement, 0.5);
+            }
+            if ui
+                .add_enabled(
+                    self.system.spring_model == SpringModel::Exact,
+                    egui::Button::new("Longitudinal"),
+                )
+                .clicked()
+            {
+                self.animate = false;
+                self.system.reset(0, self.size, 0.0);
                 self.time = 0.0;
-                self.system.pluck(self.initial_displacement);
+                self.system.longitudinal_state(
+                    self.initial_displacement,
+                    self.display_settings.harmonic_value,
+                );
+            }
+        });
+
+        egui::CollapsingHeader::new("Pulse Shapes").show(ui, |ui| {
+            egui::ComboBox::from_label("Shape")
+                .selected_text(self.pulse.kind.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in [
+                        PulseKind::Pluck,
+                        PulseKind::Gaussian,
+                        PulseKind::Square,
+                        PulseKind::WavePacket,
+                    ] {
+                        ui.selectable_value(&mut self.pulse.kind, kind, kind.to_string());
+                    }
+                });
+            egui::ComboBox::from_label("Launch")
+                .selected_text(self.pulse.travel.to_string())
+                .show_ui(ui, |ui| {
+                    for travel in [Travel::Standing, Travel::Right, Travel::Left] {
+                        ui.selectable_value(&mut self.pulse.travel, travel, travel.to_string());
+                    }
+                });
+            ui.add(egui::Slider::new(&mut self.pulse.centre, 0.01..=0.99).text(
+                if self.pulse.kind == PulseKind::Pluck {
+                    "Pluck position"
+                } else {
+                    "Centre"
+                },
+            ));
+            if self.pulse.kind != PulseKind::Pluck {
+                ui.add(egui::Slider::new(&mut self.pulse.width, 0.001..=1.0).text("Width"));
+            }
+            if self.pulse.kind == PulseKind::WavePacket {
+                ui.add(
+                    egui::Slider::new(&mut self.pulse.wavelength, 0.001..=1.0).text("Wavelength"),
+                );
+            }
+            if ui.button("Load pulse").clicked() {
+                self.animate = false;
+                self.system.reset(0, self.size, 0.0);
+                self.time = 0.0;
+                let shape = self.pulse.shape(self.size, self.initial_displacement);
+                self.system.launch(&shape, self.pulse.travel);
+            }
+        });
+
+        egui::CollapsingHeader::new("Mode Mixer").show(ui, |ui| {
+            let mut removed = None;
+            egui::Grid::new("modes").striped(true).show(ui, |ui| {
+                ui.label("Mode");
+                ui.label("Amplitude");
+                ui.label("Phase");
+                ui.label("Frequency");
+                ui.end_row();
+                for (i, mode) in self.modes.iter_mut().enumerate() {
+                    ui.add(egui::DragValue::new(&mut mode.number).clamp_range(1..=self.size));
+                    ui.add(egui::DragValue::new(&mut mode.amplitude).speed(0.01));
+                    ui.add(
+                        egui::DragValue::new(&mut mode.phase)
+                            .speed(0.01)
+                            .clamp_range(-std::f64::consts::PI..=std::f64::consts::PI)
+                            .suffix(" rad"),
+                    );
+                    ui.label(format!("{:.4}", self.system.eigenfrequency(mode.number)));
+                    if ui.button("Remove").clicked() {
+                        removed = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(i) = removed {
+                self.modes.remove(i);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Add mode").clicked() {
+                    let number = self.modes.last().map_or(1, |mode| mode.number + 1);
+                    self.modes.push(Mode {
+                        number: number.min(self.size),
+                        ..Default::default()
+                    });
+                }
+                if ui.button("Load superposition").clicked() {
+                    self.animate = false;
+                    self.system.reset(0, self.size, 0.0);
+                    self.time = 0.0;
+                    for mode in self.modes.iter_mut() {
+                        mode.number = mode.number.min(self.size);
+                    }
+                    self.system.superposition(&self.modes);
+                }
+            });
+        });
+
+        egui::CollapsingHeader::new("Expression State").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("f(x) =");
+                ui.text_edit_singleline(&mut self.expression.displacement);
+            });
+            ui.horizontal(|ui| {
+                ui.label("g(x) =");
+                ui.text_edit_singleline(&mut self.expression.velocity);
+            });
+            match self.expression.sample(self.size) {
+                Ok((shape, velocity)) => {
+                    let n = self.size;
+                    let points = |values: &[f64]| {
+                        Values::from_values_iter((0..n + 2).map(|i| {
+                            let y = if i == 0 || i == n + 1 {
+                                0.0
+                            } else {
+                                values[i - 1]
+                            };
+                            Value::new(i as f64 / (n + 1) as f64, y)
+                        }))
+                    };
+                    Plot::new("Expression preview")
+                        .height(120.0)
+                        .legend(Legend::default())
+                        .show(ui, |plot_ui| {
+                            plot_ui.line(
+                                Line::new(points(&shape))
+                                    .name("f(x)")
+                                    .color(self.display_settings.display_colors[1]),
+                            );
+                            plot_ui.line(
+                                Line::new(points(&velocity))
+                                    .name("g(x)")
+                                    .color(self.display_settings.display_colors[0]),
+                            );
+                        });
+                    if ui.button("Apply").clicked() {
+                        self.animate = false;
+                        self.system.reset(0, self.size, 0.0);
+                        self.time = 0.0;
+                        self.system.set_state(&shape, &velocity);
+                    }
+                }
+                Err(error) => {
+                    ui.colored_label(Color32::RED, error);
+                }
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut self.expression.name);
+            });
+            if ui.button("Save expression").clicked() {
+                match self
+                    .saved_expressions
+                    .iter_mut()
+                    .find(|saved| saved.name == self.expression.name)
+                {
+                    Some(saved) => *saved = self.expression.clone(),
+                    None => self.saved_expressions.push(self.expression.clone()),
+                }
+            }
+            let mut removed = None;
+            for (i, saved) in self.saved_expressions.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.button("Load").clicked() {
+                        self.expression = saved.clone();
+                    }
+                    if ui.button("Delete").clicked() {
+                        removed = Some(i);
+                    }
+                    ui.label(&saved.name);
+                });
+            }
+            if let Some(i) = removed {
+                self.saved_expressions.remove(i);
             }
         });
 
+        egui::CollapsingHeader::new("Hammer").show(ui, |ui| {
+            let hammer = &mut self.system.hammer;
+            ui.add(egui::Slider::new(&mut hammer.position, 0.0..=1.0).text("Strike position"));
+            ui.add(
+                egui::DragValue::new(&mut hammer.strike_velocity)
+                    .speed(0.01)
+                    .clamp_range(0.0..=f64::INFINITY)
+                    .prefix("Strike velocity: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut hammer.start_distance)
+                    .speed(0.01)
+                    .clamp_range(0.0..=f64::INFINITY)
+                    .prefix("Start distance: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut hammer.mass)
+                    .speed(0.01)
+                    .clamp_range(0.01..=f64::INFINITY)
+                    .prefix("Hammer mass: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut hammer.felt_stiffness)
+                    .speed(0.1)
+                    .clamp_range(0.0..=f64::INFINITY)
+                    .prefix("Felt stiffness: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut hammer.felt_exponent)
+                    .speed(0.01)
+                    .clamp_range(1.0..=5.0)
+                    .prefix("Felt exponent: "),
+            );
+            if ui.button("Strike").clicked() {
+                self.system.strike();
+                self.animate = true;
+            }
+            ui.label(if self.system.hammer.in_contact() {
+                "Hammer: in contact"
+            } else if self.system.hammer.in_flight() {
+                "Hammer: in flight"
+            } else {
+                "Hammer: at rest"
+            });
+            let history = self.system.hammer.force_history();
+            if !history.is_empty() {
+                ui.label("Contact force");
+                Plot::new("Hammer force")
+                    .height(120.0)
+                    .include_y(0.0)
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(Line::new(Values::from_values(
+                            history.iter().map(|&[t, f]| Value::new(t, f)).collect(),
+                        )));
+                    });
+            }
+        });
+
+        egui::CollapsingHeader::new("Bow").show(ui, |ui| {
+            let bow = &mut self.system.bow;
+            ui.checkbox(&mut bow.enabled, "Bowing");
+            ui.add(egui::Slider::new(&mut bow.position, 0.0..=1.0).text("Bow position"));
+            ui.add(
+                egui::DragValue::new(&mut bow.velocity)
+                    .speed(0.01)
+                    .prefix("Bow velocity: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut bow.force)
+                    .speed(0.01)
+                    .clamp_range(0.0..=f64::INFINITY)
+                    .prefix("Bow force: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut bow.static_friction)
+                    .speed(0.01)
+                    .clamp_range(0.0..=f64::INFINITY)
+                    .prefix("Static friction: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut bow.dynamic_friction)
+                    .speed(0.01)
+                    .clamp_range(0.0..=f64::INFINITY)
+                    .prefix("Dynamic friction: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut bow.slip_velocity)
+                    .speed(0.001)
+                    .clamp_range(0.001..=f64::INFINITY)
+                    .prefix("Slip velocity: "),
+            );
+            if bow.enabled {
+                ui.label(if bow.sticking() {
+                    "Bow: sticking"
+                } else {
+                    "Bow: slipping"
+                });
+            }
+            if ui.button("Clear history").clicked() {
+                self.bow_history.clear();
+            }
+            let history = self.bow_history.samples();
+            if !history.is_empty() {
+                ui.label("Bowed mass velocity");
+                Plot::new("Bowed velocity")
+                    .height(120.0)
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(Line::new(Values::from_values(
+                            history.iter().map(|&[t, v]| Value::new(t, v)).collect(),
+                        )));
+                    });
+            }
+        });
+
+        egui::CollapsingHeader::new("Obstacles").show(ui, |ui| {
+            egui::ComboBox::from_label("Contact model")
+                .selected_text(self.system.contact_model.to_string())
+                .show_ui(ui, |ui| {
+                    for model in [ContactModel::Penalty, ContactModel::Exact] {
+                        ui.selectable_value(
+                            &mut self.system.contact_model,
+                            model,
+                            model.to_string(),
+                        );
+                    }
+                });
+            ui.add_enabled(
+                self.system.contact_model == ContactModel::Penalty,
+                egui::DragValue::new(&mut self.system.contact_stiffness)
+                    .speed(0.1)
+                    .clamp_range(0.0..=f64::INFINITY)
+                    .prefix("Contact stiffness: "),
+            );
+            let mut removed = None;
+            for (i, obstacle) in self.system.obstacles.iter_mut().enumerate() {
+                ui.separator();
+                egui::ComboBox::from_id_source(("obstacle shape", i))
+                    .selected_text(obstacle.shape.to_string())
+                    .show_ui(ui, |ui| {
+                        for shape in [
+                            ObstacleShape::Fret,
+                            ObstacleShape::Bridge,
+                            ObstacleShape::Wall,
+                        ] {
+                            ui.selectable_value(&mut obstacle.shape, shape, shape.to_string());
+                        }
+                    });
+                ui.add(egui::Slider::new(&mut obstacle.position, 0.0..=1.0).text("Position"));
+                ui.add(
+                    egui::DragValue::new(&mut obstacle.height)
+                        .speed(0.01)
+                        .prefix("Height: "),
+                );
+                if obstacle.shape != ObstacleShape::Fret {
+                    ui.add(egui::Slider::new(&mut obstacle.width, 0.0..=1.0).text("Width"));
+                }
+                if obstacle.shape == ObstacleShape::Bridge {
+                    ui.add(
+                        egui::DragValue::new(&mut obstacle.curvature)
+                            .speed(0.1)
+                            .prefix("Curvature: "),
+                    );
+                }
+                if ui.button("Remove").clicked() {
+                    removed = Some(i);
+                }
+            }
+            if let Some(i) = removed {
+                self.system.obstacles.remove(i);
+            }
+            ui.separator();
+            if ui.button("Add obstacle").clicked() {
+                self.system.obstacles.push(Default::default());
+            }
+        });
+
+        egui::CollapsingHeader::new("Probes").show(ui, |ui| {
+            ui.checkbox(&mut self.display_settings.show_probes, "Show probe plot");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut self.probe_mass)
+                        .clamp_range(0..=self.size - 1)
+                        .prefix("Mass: "),
+                );
+                if ui.button("Add probe").clicked() {
+                    self.probes.add(self.probe_mass);
+                }
+            });
+            ui.add(
+                egui::DragValue::new(&mut self.probes.capacity)
+                    .speed(10.0)
+                    .clamp_range(10..=1_000_000)
+                    .prefix("Samples kept: "),
+            );
+            let mut removed = None;
+            for (i, probe) in self.probes.probes.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_srgba(&mut probe.color);
+                    let mass = ui.add(
+                        egui::DragValue::new(&mut probe.mass)
+                            .clamp_range(0..=self.size - 1)
+                            .prefix("Mass: "),
+                    );
+                    if mass.changed() {
+                        probe.clear();
+                    }
+                    ui.label(format!("{} samples", probe.samples().len()));
+                    if ui.button("Remove").clicked() {
+                        removed = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = removed {
+                self.probes.probes.remove(i);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Clear").clicked() {
+                    self.probes.clear();
+                }
+                if ui.button("Copy CSV").clicked() {
+                    ui.output().copied_text = self.probes.to_csv();
+                    self.export_status = "Copied to clipboard".to_string();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.export_path);
+                if ui.button("Save CSV").clicked() {
+                    self.export_status =
+                        save_file(&self.export_path, self.probes.to_csv().as_bytes());
+                }
+            });
+        });
+
+        egui::CollapsingHeader::new("Frequency Analysis").show(ui, |ui| {
+            if self.probes.probes.is_empty() {
+                ui.label("Add a probe to analyse its time series.");
+                return;
+            }
+            self.analysis_probe = self.analysis_probe.min(self.probes.probes.len() - 1);
+            egui::ComboBox::from_label("Probe")
+                .selected_text(format!(
+                    "{}: mass {}",
+                    self.analysis_probe, self.probes.probes[self.analysis_probe].mass
+                ))
+                .show_ui(ui, |ui| {
+                    for (i, probe) in self.probes.probes.iter().enumerate() {
+                        ui.selectable_value(
+                            &mut self.analysis_probe,
+                            i,
+                            format!("{}: mass {}", i, probe.mass),
+                        );
+                    }
+                });
+            egui::ComboBox::from_label("Window length")
+                .selected_text(self.spectrogram_window.to_string())
+                .show_ui(ui, |ui| {
+                    for window in [64, 128, 256, 512, 1024, 2048] {
+                        ui.selectable_value(
+                            &mut self.spectrogram_window,
+                            window,
+                            window.to_string(),
+                        );
+                    }
+                });
+            ui.checkbox(
+                &mut self.display_settings.show_spectrogram,
+                "Show spectrogram",
+            );
+            let key = self.peak_key();
+            let stale = self.peaks.as_ref().map_or(true, |(k, _)| *k != key);
+            let mut estimate = stale && self.live_peaks;
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.live_peaks, "Live")
+                    .on_hover_text("Estimate the peaks again whenever new samples are recorded");
+                estimate |= ui.button("Estimate peaks").clicked();
+            });
+            if estimate {
+                let peaks = estimate_peaks(&self.analysis_samples(), self.delta, 5);
+                self.peaks = Some((key, peaks));
+            }
+            let peaks = self
+                .peaks
+                .as_ref()
+                .map_or_else(Vec::new, |(_, peaks)| peaks.clone());
+            egui::Grid::new("peaks").striped(true).show(ui, |ui| {
+                ui.label("Measured");
+                ui.label("Mode");
+                ui.label("Theory");
+                ui.label("Error");
+                ui.label("Amplitude");
+                ui.end_row();
+                for peak in peaks {
+                    let mode = self.nearest_mode(peak.frequency);
+                    let theory = self.system.eigenfrequency(mode);
+                    ui.label(format!("{:.5}", peak.frequency));
+                    ui.label(mode.to_string());
+                    ui.label(format!("{:.5}", theory));
+                    ui.label(format!(
+                        "{:.3}%",
+                        100.0 * (peak.frequency - theory) / theory
+                    ));
+                    ui.label(format!("{:.4}", peak.amplitude));
+                    ui.end_row();
+                }
+            });
+        });
+
+        egui::CollapsingHeader::new("Phase Space").show(ui, |ui| {
+            ui.checkbox(&mut self.display_settings.show_phase, "Show phase space");
+            let phase = &mut self.phase;
+            let before = (phase.kind, phase.mass, phase.mode_x, phase.mode_y);
+            egui::ComboBox::from_label("Coordinates")
+                .selected_text(phase.kind.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in [PhaseKind::Mass, PhaseKind::Modes] {
+                        ui.selectable_value(&mut phase.kind, kind, kind.to_string());
+                    }
+                });
+            match phase.kind {
+                PhaseKind::Mass => {
+                    ui.add(
+                        egui::DragValue::new(&mut phase.mass)
+                            .clamp_range(0..=self.size - 1)
+                            .prefix("Mass: "),
+                    );
+                }
+                PhaseKind::Modes => {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut phase.mode_x)
+                                .clamp_range(1..=self.size)
+                                .prefix("Mode x: "),
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut phase.mode_y)
+                                .clamp_range(1..=self.size)
+                                .prefix("Mode y: "),
+                        );
+                    });
+                }
+            }
+            if before != (phase.kind, phase.mass, phase.mode_x, phase.mode_y) {
+                phase.clear();
+            }
+            ui.checkbox(&mut phase.poincare, "Poincaré section");
+            ui.add_enabled_ui(phase.poincare, |ui| {
+                egui::ComboBox::from_label("Section coordinate")
+                    .selected_text(phase.section_source.to_string())
+                    .show_ui(ui, |ui| {
+                        for source in [
+                            SectionSource::MassDisplacement,
+                            SectionSource::ModeAmplitude,
+                        ] {
+                            ui.selectable_value(
+                                &mut phase.section_source,
+                                source,
+                                source.to_string(),
+                            );
+                        }
+                    });
+                let (range, prefix) = match phase.section_source {
+                    SectionSource::MassDisplacement => (0..=self.size - 1, "Section mass: "),
+                    SectionSource::ModeAmplitude => (1..=self.size, "Section mode: "),
+                };
+                ui.add(
+                    egui::DragValue::new(&mut phase.section_index)
+                        .clamp_range(range)
+                        .prefix(prefix),
+                );
+                ui.label(format!("{} section points", phase.section().len()));
+            });
+            if ui.button("Clear").clicked() {
+                phase.clear();
+            }
+        });
+
+        egui::CollapsingHeader::new("Space-Time View").show(ui, |ui| {
+            ui.checkbox(
+                &mut self.display_settings.show_kymograph,
+                "Show space-time view",
+            );
+            let kymograph = &mut self.kymograph;
+            egui::ComboBox::from_label("Colour map")
+                .selected_text(kymograph.colormap.to_string())
+                .show_ui(ui, |ui| {
+                    for colormap in [Colormap::Viridis, Colormap::Grayscale, Colormap::Diverging] {
+                        ui.selectable_value(
+                            &mut kymograph.colormap,
+                            colormap,
+                            colormap.to_string(),
+                        );
+                    }
+                });
+            ui.add(
+                egui::DragValue::new(&mut kymograph.capacity)
+                    .speed(10.0)
+                    .clamp_range(10..=100_000)
+                    .prefix("Steps kept: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut kymograph.visible)
+                    .speed(10.0)
+                    .clamp_range(10..=100_000)
+                    .prefix("Steps shown: "),
+            );
+            let back = kymograph.len().saturating_sub(kymograph.visible);
+            ui.add_enabled(
+                back > 0,
+                egui::Slider::new(&mut kymograph.scroll, 0..=back).text("Steps back"),
+            );
+            if ui.button("Clear").clicked() {
+                kymograph.clear();
+            }
+        });
+
+        egui::CollapsingHeader::new("Dispersion").show(ui, |ui| {
+            ui.checkbox(
+                &mut self.display_settings.show_dispersion,
+                "Show dispersion",
+            );
+            ui.label(format!(
+                "{} steps of space-time history recorded",
+                self.kymograph.len()
+            ));
+            if ui
+                .add_enabled(self.kymograph.len() >= 16, egui::Button::new("Compute"))
+                .clicked()
+            {
+                self.compute_dispersion(ui.ctx());
+                self.display_settings.show_dispersion = true;
+            }
+        });
+
+        egui::CollapsingHeader::new("Plot Export").show(ui, |ui| {
+            let export = &mut self.plot_export;
+            egui::ComboBox::from_label("Image format")
+                .selected_text(export.format.to_string())
+                .show_ui(ui, |ui| {
+                    for format in [PlotFormat::Svg, PlotFormat::Png] {
+                        if ui
+                            .selectable_value(&mut export.format, format, format.to_string())
+                            .clicked()
+                        {
+                            let path = std::path::Path::new(&self.plot_path);
+                            self.plot_path = path
+                                .with_extension(format.extension())
+                                .to_string_lossy()
+                                .to_string();
+                        }
+                    }
+                });
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut export.width)
+                        .clamp_range(16..=4096)
+                        .prefix("Width: "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut export.height)
+                        .clamp_range(16..=4096)
+                        .prefix("Height: "),
+                );
+            });
+            if export.format == PlotFormat::Png {
+                ui.add(
+                    egui::Slider::new(&mut export.scale, 1.0..=4.0)
+                        .step_by(0.5)
+                        .text("Pixels per point"),
+                );
+            }
+            ui.checkbox(&mut export.axes, "Axes");
+            ui.checkbox(&mut export.equal_aspect, "Equal aspect");
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.plot_path);
+                if ui.button("Export plot").clicked() {
+                    self.export_status = match self
+                        .plot_export
+                        .render(&self.system, &self.display_settings)
+                    {
+                        Ok(bytes) => save_file(&self.plot_path, &bytes),
+                        Err(error) => error,
+                    };
+                }
+            });
+        });
+
+        egui::CollapsingHeader::new("Audio Export").show(ui, |ui| {
+            let pickup = &mut self.pickup;
+            ui.add(egui::Slider::new(&mut pickup.position, 0.0..=1.0).text("Pickup position"));
+            ui.add(egui::Slider::new(&mut pickup.width, 0.0..=0.2).text("Pickup width"));
+            egui::ComboBox::from_label("Pickup quantity")
+                .selected_text(pickup.quantity.to_string())
+                .show_ui(ui, |ui| {
+                    for quantity in [PickupQuantity::Displacement, PickupQuantity::Velocity] {
+                        ui.selectable_value(&mut pickup.quantity, quantity, quantity.to_string());
+                    }
+                });
+            ui.add(
+                egui::DragValue::new(&mut pickup.pitch)
+                    .speed(1.0)
+                    .clamp_range(20.0..=5000.0)
+                    .prefix("Pitch (Hz): "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut pickup.duration)
+                    .speed(0.1)
+                    .clamp_range(0.1..=30.0)
+                    .prefix("Duration (s): "),
+            );
+            egui::ComboBox::from_label("Sample rate")
+                .selected_text(format!("{} Hz", pickup.sample_rate))
+                .show_ui(ui, |ui| {
+                    for rate in [22_050, 44_100, 48_000, 96_000] {
+                        ui.selectable_value(&mut pickup.sample_rate, rate, format!("{} Hz", rate));
+                    }
+                });
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut pickup.bits, 16, "16-bit");
+                ui.radio_value(&mut pickup.bits, 24, "24-bit");
+            });
+            ui.label(format!(
+                "{:.0} time units per second of audio",
+                pickup.time_scale(&self.system)
+            ));
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.audio_path);
+                if ui.button("Export WAV").clicked() {
+                    self.export_status =
+                        match self.pickup.render(&self.system, self.time, self.delta) {
+                            Ok(samples) => save_file(
+                                &self.audio_path,
+                                &wav(&samples, self.pickup.sample_rate, self.pickup.bits),
+                            ),
+                            Err(error) => error,
+                        };
+                }
+            });
+        });
+
+        egui::CollapsingHeader::new("Animation Export").show(ui, |ui| {
+            let animation = &mut self.animation;
+            egui::ComboBox::from_label("Format")
+                .selected_text(animation.format.to_string())
+                .show_ui(ui, |ui| {
+                    for format in [AnimationFormat::Gif, AnimationFormat::Apng] {
+                        if ui
+                            .selectable_value(&mut animation.format, format, format.to_string())
+                            .clicked()
+                        {
+                            let path = std::path::Path::new(&self.animation_path);
+                            self.animation_path = path
+                                .with_extension(format.extension())
+                                .to_string_lossy()
+                                .to_string();
+                        }
+                    }
+                });
+            ui.add(
+                egui::DragValue::new(&mut animation.span)
+                    .speed(0.1)
+                    .clamp_range(0.1..=f64::INFINITY)
+                    .prefix("Time span: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut animation.speed)
+                    .speed(0.1)
+                    .clamp_range(0.01..=f64::INFINITY)
+                    .prefix("Time per second: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut animation.frame_rate)
+                    .clamp_range(1..=50)
+                    .prefix("Frames per second: "),
+            );
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut animation.width)
+                        .clamp_range(16..=1920)
+                        .prefix("Width: "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut animation.height)
+                        .clamp_range(16..=1080)
+                        .prefix("Height: "),
+                );
+            });
+            ui.label(match animation.size(self.delta) {
+                Ok((frames, steps)) => format!("{} frames, {} steps each", frames, steps),
+                Err(error) => error,
+            });
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.animation_path);
+                if ui.button("Export animation").clicked() {
+                    self.export_status = match self.animation.render(
+                        &self.system,
+                        self.time,
+                        self.delta,
+                        &self.display_settings,
+                    ) {
+                        Ok(bytes) => save_file(&self.animation_path, &bytes),
+                        Err(error) => error,
+                    };
+                }
+            });
+        });
+
         egui::CollapsingHeader::new("Clamped Settings").show(ui, |ui| {
             ui.add(
                 egui::DragValue::new(&mut self.max_time)
@@ -238,6 +1937,14 @@ impl SystemPlot {
         });
 
         egui::CollapsingHeader::new("Mass positions").show(ui, |ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.insert_inertia)
+                    .speed(0.01)
+                    .clamp_range(0.01..=100.0)
+                    .prefix("Inserted mass: "),
+            );
+            let mut inserted = None;
+            let mut removed = None;
             egui::ScrollArea::vertical().show(ui, |ui| {
                 for i in 0..self.size {
                     let mut x = self.system.get_mass_pos(i);
@@ -246,8 +1953,70 @@ impl SystemPlot {
                     if mass_response.changed() {
                         self.system.alter(i, x);
                     }
+                    if self.system.spring_model == SpringModel::Exact {
+                        let mut u = self.system.get_mass_long_pos(i);
+                        let long_response = ui.add(
+                            egui::Slider::new(&mut u, -1.0..=1.0).text(format!("{} (x): ", i)),
+                        );
+                        if long_response.changed() {
+                            self.system.alter_long(i, u);
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        if self.system.is_pinned(i) {
+                            if ui.button("Release").clicked() {
+                                self.system.release(i);
+                            }
+                        } else {
+                            if ui.button("Pin at 0").clicked() {
+                                self.system.pin(i, 0.0);
+                            }
+                            if ui.button("Pin here").clicked() {
+                                self.system.pin(i, self.system.get_mass_pos(i));
+                            }
+                        }
+                        let mut finger = self.system.get_finger(i);
+                        let finger_response = ui.add(
+                            egui::DragValue::new(&mut finger)
+                                .speed(0.01)
+                                .clamp_range(0.0..=5.0)
+                                .prefix("Finger: "),
+                        );
+                        if finger_response.changed() {
+                            self.system.set_finger(i, finger);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let mut inertia = self.system.get_inertia(i);
+                        let inertia_response = ui.add(
+                            egui::DragValue::new(&mut inertia)
+                                .speed(0.01)
+                                .clamp_range(0.01..=100.0)
+                                .prefix("Mass: "),
+                        );
+                        if inertia_response.changed() {
+                            self.system.set_inertia(i, inertia);
+                        }
+                        if ui.button("Insert before").clicked() {
+                            inserted = Some(i);
+                        }
+                        if ui.button("Remove").clicked() {
+                            removed = Some(i);
+                        }
+                    });
                 }
-            })
+                if ui.button("Append").clicked() {
+                    inserted = Some(self.size);
+                }
+            });
+            if let (Some(i), true) = (inserted, self.size < 500) {
+                self.system.insert(i, self.insert_inertia);
+                self.size += 1;
+            }
+            if let (Some(i), true) = (removed, self.size > 1) {
+                self.system.remove(i);
+                self.size -= 1;
+            }
         });
         egui::warn_if_debug_build(ui);
     }
@@ -267,6 +2036,27 @@ impl eframe::App for SystemPlot {
             ..
         } = self;
 
+        self.undo_stack.begin_frame(self.snapshot());
+        if !ctx.wants_keyboard_input() {
+            let redo = egui::Modifiers {
+                shift: true,
+                ..egui::Modifiers::COMMAND
+            };
+            let (undo, redo) = {
+                let mut input = ctx.input_mut();
+                (
+                    input.consume_key(egui::Modifiers::COMMAND, egui::Key::Z),
+                    input.consume_key(redo, egui::Key::Z),
+                )
+            };
+            if undo {
+                self.undo(false);
+            }
+            if redo {
+                self.undo(true);
+            }
+        }
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
@@ -277,7 +2067,60 @@ impl eframe::App for SystemPlot {
                         self.display_settings.windowed = !self.display_settings.windowed;
                     }
                     if ui.button("Full Reset").clicked() {
+                        // Keep the undo history, so a full reset can be undone, and the
+                        // libraries, which undo does not restore.
+                        let undo_stack = std::mem::take(&mut self.undo_stack);
+                        let saved_expressions = std::mem::take(&mut self.saved_expressions);
+                        let presets = std::mem::take(&mut self.presets);
                         *self = Default::default();
+                        self.undo_stack = undo_stack;
+                        self.saved_expressions = saved_expressions;
+                        self.presets = presets;
+                    }
+                    ui.separator();
+                    ui.text_edit_singleline(&mut self.scenario_path);
+                    if ui.button("Save Scenario").clicked() {
+                        self.export_status = match self.scenario().to_ron() {
+                            Ok(text) => save_file(&self.scenario_path, text.as_bytes()),
+                            Err(error) => format!("Could not save scenario: {}", error),
+                        };
+                        ui.close_menu();
+                    }
+                    if ui.button("Load Scenario").clicked() {
+                        self.export_status = match load_file(&self.scenario_path)
+                            .and_then(|text| Scenario::from_ron(&text))
+                        {
+                            Ok(scenario) => {
+                                self.load_scenario(scenario);
+                                format!("Loaded {}", self.scenario_path)
+                            }
+                            Err(error) => {
+                                format!("Could not load {}: {}", self.scenario_path, error)
+                            }
+                        };
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Edit", |ui| {
+                    if ui
+                        .add_enabled(
+                            self.undo_stack.can_undo(),
+                            egui::Button::new("Undo (Ctrl+Z)"),
+                        )
+                        .clicked()
+                    {
+                        self.undo(false);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(
+                            self.undo_stack.can_redo(),
+                            egui::Button::new("Redo (Ctrl+Shift+Z)"),
+                        )
+                        .clicked()
+                    {
+                        self.undo(true);
+                        ui.close_menu();
                     }
                 });
             });
@@ -292,14 +2135,64 @@ impl eframe::App for SystemPlot {
             });
         }
 
+        if self.display_settings.show_spectrum {
+            egui::TopBottomPanel::bottom("spectrum_panel")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    self.spectrum(ui);
+                });
+        }
+
+        if self.display_settings.show_spectrogram && !self.probes.probes.is_empty() {
+            self.analysis_probe = self.analysis_probe.min(self.probes.probes.len() - 1);
+            egui::TopBottomPanel::bottom("spectrogram_panel")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    self.spectrogram_plot(ui);
+                });
+        }
+
+        if self.display_settings.show_dispersion {
+            egui::TopBottomPanel::bottom("dispersion_panel")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    self.dispersion_plot(ui);
+                });
+        }
+
+        if self.display_settings.show_kymograph {
+            egui::TopBottomPanel::bottom("kymograph_panel")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    self.kymograph_plot(ui);
+                });
+        }
+
+        if self.display_settings.show_phase {
+            egui::TopBottomPanel::bottom("phase_panel")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    self.phase_plot(ui);
+                });
+        }
+
+        if self.display_settings.show_probes && !self.probes.probes.is_empty() {
+            egui::TopBottomPanel::bottom("probe_panel")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    self.probe_plot(ui);
+                });
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.ctx().request_repaint();
 
             let plot = Plot::new("Loaded String")
                 .legend(Legend::default())
-                .data_aspect(1.0);
+                .data_aspect(1.0)
+                .allow_drag(self.display_settings.plot_tool == PlotTool::Pin);
 
-            plot.show(ui, |plot_ui| {
+            let response = plot.show(ui, |plot_ui| {
                 plot_ui.line(
                     self.line_points(self.display_settings.width)
                         .color(self.display_settings.display_colors[1]),
@@ -308,6 +2201,70 @@ impl eframe::App for SystemPlot {
                     self.circle_points(self.display_settings.radius, self.display_settings.width)
                         .color(self.display_settings.display_colors[2]),
                 );
+                let width = self.display_settings.width;
+                let n = self.size;
+                let pinned: Vec<Value> = (0..n)
+                    .filter(|&i| self.system.is_pinned(i))
+                    .map(|i| {
+                        Value::new(
+                            ((i as f64 + 1.0 + self.system.get_mass_long_pos(i))
+                                / ((n + 1) as f64))
+                                * width,
+                            self.system.get_mass_pos(i),
+                        )
+                    })
+                    .collect();
+                if !pinned.is_empty() {
+                    plot_ui.points(
+                        Points::new(Values::from_values(pinned))
+                            .name("pinned")
+                            .filled(false)
+                            .radius(self.display_settings.radius * 1.5)
+                            .shape(MarkerShape::Diamond)
+                            .color(self.display_settings.display_colors[0]),
+                    );
+                }
+                for obstacle in &self.system.obstacles {
+                    let outline: Vec<Value> = obstacle
+                        .outline()
+                        .iter()
+                        .map(|&[x, y]| Value::new(x * width, y))
+                        .collect();
+                    let color = self.display_settings.display_colors[0];
+                    if obstacle.shape == ObstacleShape::Fret {
+                        plot_ui.points(
+                            Points::new(Values::from_values(outline))
+                                .name("obstacle")
+                                .filled(true)
+                                .radius(self.display_settings.radius)
+                                .shape(MarkerShape::Up)
+                                .color(color),
+                        );
+                    } else {
+                        plot_ui.line(
+                            Line::new(Values::from_values(outline))
+                                .name("obstacle")
+                                .width(2.0)
+                                .color(color),
+                        );
+                    }
+                }
+                if self.system.hammer.in_flight() {
+                    let n = self.size;
+                    let target = self.system.hammer.target(n);
+                    plot_ui.points(
+                        Points::new(Values::from_values(vec![Value::new(
+                            ((target as f64 + 1.0) / ((n + 1) as f64))
+                                * self.display_settings.width,
+                            self.system.hammer.get_pos(),
+                        )]))
+                        .name("hammer")
+                        .filled(true)
+                        .radius(self.display_settings.radius * 1.5)
+                        .shape(MarkerShape::Square)
+                        .color(self.display_settings.display_colors[0]),
+                    );
+                }
                 if self.display_settings.display_colors[0] != Color32::TRANSPARENT {
                     if self.display_settings.boundary_style == "line" {
                         plot_ui
@@ -327,14 +2284,88 @@ impl eframe::App for SystemPlot {
                         );
                     }
                 }
+                if self.stroke.len() > 1 {
+                    plot_ui.line(
+                        Line::new(Values::from_values(
+                            self.stroke.iter().map(|&[x, y]| Value::new(x, y)).collect(),
+                        ))
+                        .name("stroke")
+                        .style(LineStyle::dashed_loose())
+                        .color(self.display_settings.display_colors[1]),
+                    );
+                }
+                (
+                    self.mass_under_pointer(plot_ui),
+                    plot_ui.pointer_coordinate(),
+                    plot_ui.plot_clicked(),
+                )
             });
+            let (hovered, pointer, clicked) = response.inner;
+            let (pressed, down) = {
+                let input = ui.input();
+                (input.pointer.any_pressed(), input.pointer.primary_down())
+            };
+            match self.display_settings.plot_tool {
+                PlotTool::Pin => {
+                    if let (true, Some(i)) = (clicked, hovered) {
+                        if self.system.is_pinned(i) {
+                            self.system.release(i);
+                        } else {
+                            self.system.pin(i, self.system.get_mass_pos(i));
+                        }
+                    }
+                }
+                PlotTool::Drag => {
+                    if pressed {
+                        self.dragged = hovered;
+                    }
+                    if !down {
+                        self.dragged = None;
+                    }
+                    if let (Some(i), Some(pointer)) = (self.dragged, pointer) {
+                        self.system.alter(i, pointer.y);
+                    }
+                }
+                PlotTool::Draw => {
+                    // Only presses on the plot itself start a stroke, not the side panel or menus.
+                    let plot = &response.response;
+                    if plot.drag_started() {
+                        self.stroke.clear();
+                    }
+                    if let (true, Some(pointer)) = (plot.drag_started() || plot.dragged(), pointer)
+                    {
+                        self.stroke.push([pointer.x, pointer.y]);
+                    } else if plot.drag_released() && !self.stroke.is_empty() {
+                        self.apply_stroke();
+                        self.stroke.clear();
+                    }
+                }
+            }
         });
 
-        if self.animate {
+        let pointer_down = ctx.input().pointer.any_down();
+        let changed = self
+            .undo_stack
+            .before()
+            .map_or(false, |before| !self.matches(before));
+        self.undo_stack.end_frame(changed, pointer_down);
+
+        if self.rewinding {
+            match self.history.cursor() {
+                Some(step) if step > self.history.range().0 => self.seek(step - 1),
+                _ => self.rewinding = false,
+            }
+        }
+        if self.animate && self.reverse {
+            if self.time <= 0.5 * self.delta {
+                self.animate = false;
+            }
+            self.step_back();
+        } else if self.animate {
             if round(self.time, self.delta) >= self.max_time && self.clamped {
                 self.animate = false;
             } else {
-                self.system.update_system(&mut self.time, self.delta);
+                self.step();
             }
         }
     }
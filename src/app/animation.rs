@@ -0,0 +1,193 @@
+use super::display::DisplaySettings;
+use super::figure::string_figure;
+use super::string_dynamics::Sys;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+
+/// Largest number of frames in an exported animation.
+pub const MAX_FRAMES: usize = 1000;
+
+/// Largest number of simulation steps run for one animation, over all frames.
+pub const MAX_STEPS: usize = 1_000_000;
+
+/// File format of exported animations.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+impl std::fmt::Display for AnimationFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnimationFormat::Gif => write!(f, "GIF"),
+            AnimationFormat::Apng => write!(f, "APNG"),
+        }
+    }
+}
+
+impl AnimationFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+        }
+    }
+}
+
+/// Settings of an animation rendered without a window: `span` units of
+/// simulation time are played back at `speed` units per second.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub format: AnimationFormat,
+    pub span: f64,
+    pub speed: f64,
+    pub frame_rate: u16,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Self {
+            format: AnimationFormat::Gif,
+            span: 20.0,
+            speed: 4.0,
+            frame_rate: 25,
+            width: 480,
+            height: 270,
+        }
+    }
+}
+
+impl Animation {
+    /// Number of frames and of simulation steps between frames at a
+    /// time-step of `delta`. Fails if the animation would have more than
+    /// `MAX_FRAMES` frames or run more than `MAX_STEPS` steps.
+    pub fn size(&self, delta: f64) -> Result<(usize, usize), String> {
+        let frames = (self.span / self.speed * self.frame_rate as f64).round().max(1.0);
+        if frames > MAX_FRAMES as f64 {
+            return Err(format!(
+                "The animation needs {:.0} frames, more than the limit of {}. \
+                 Shorten the time span, raise the time per second or lower the frame rate.",
+                frames, MAX_FRAMES
+            ));
+        }
+        let steps = (self.speed / self.frame_rate as f64 / delta).round().max(1.0);
+        if frames * steps > MAX_STEPS as f64 {
+            return Err(format!(
+                "The animation needs {:.0} simulation steps, more than the limit of {}. \
+                 Shorten the time span or raise the time-step.",
+                frames * steps,
+                MAX_STEPS
+            ));
+        }
+        Ok((frames as usize, steps as usize))
+    }
+
+    /// Runs a copy of `system` from `time` and encodes one frame every
+    /// `speed / frame_rate` units of time, starting with the current state.
+    pub fn render(
+        &self,
+        system: &Sys,
+        time: f64,
+        delta: f64,
+        display: &DisplaySettings,
+    ) -> Result<Vec<u8>, String> {
+        let (frames, steps) = self.size(delta)?;
+        let mut system = system.clone();
+        let mut time = time;
+        let mut states = Vec::with_capacity(frames);
+        for _ in 0..frames {
+            states.push(system.clone());
+            for _ in 0..steps {
+                system.update_system(&mut time, delta);
+            }
+        }
+        // One vertical scale for the whole animation, so the string does not
+        // appear to change amplitude from frame to frame.
+        let figures: Vec<_> = states.iter().map(|s| string_figure(s, display)).collect();
+        let extent = figures.iter().map(|f| f.extent()).fold(0.0, f64::max);
+        let frames = figures.into_iter().map(|mut figure| {
+            if extent > 0.0 {
+                figure.y_range = (-1.1 * extent, 1.1 * extent);
+            }
+            figure.raster(self.width, self.height, 1.0)
+        });
+        match self.format {
+            AnimationFormat::Gif => gif(frames, self.frame_rate),
+            AnimationFormat::Apng => apng(frames, self.frame_rate),
+        }
+        .map_err(|e| format!("Could not encode animation: {}", e))
+    }
+}
+
+fn gif(frames: impl Iterator<Item = RgbaImage>, frame_rate: u16) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut bytes, 10);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| e.to_string())?;
+        let delay = Delay::from_numer_denom_ms(1000, frame_rate as u32);
+        encoder
+            .encode_frames(frames.map(|image| Frame::from_parts(image, 0, 0, delay)))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(bytes)
+}
+
+fn apng(
+    frames: impl ExactSizeIterator<Item = RgbaImage>,
+    frame_rate: u16,
+) -> Result<Vec<u8>, String> {
+    let mut frames = frames.peekable();
+    let (width, height) = match frames.peek() {
+        Some(image) => image.dimensions(),
+        None => return Err("no frames".to_string()),
+    };
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .set_animated(frames.len() as u32, 0)
+            .map_err(|e| e.to_string())?;
+        encoder
+            .set_frame_delay(1, frame_rate)
+            .map_err(|e| e.to_string())?;
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        for image in frames {
+            writer
+                .write_image_data(image.as_raw())
+                .map_err(|e| e.to_string())?;
+        }
+        writer.finish().map_err(|e| e.to_string())?;
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_and_limits() {
+        let animation = Animation::default();
+        assert_eq!(animation.size(0.1), Ok((125, 2)));
+        let long = Animation {
+            span: 1e6,
+            ..Default::default()
+        };
+        assert!(long.size(0.1).unwrap_err().contains("frames"));
+        let fast = Animation {
+            speed: 1e6,
+            span: 1e6,
+            ..Default::default()
+        };
+        assert!(fast.size(0.1).unwrap_err().contains("steps"));
+    }
+}
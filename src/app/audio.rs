@@ -0,0 +1,279 @@
+use super::string_dynamics::{square, Sys};
+use std::f64::consts::PI;
+
+/// Number of zero crossings of the windowed sinc on each side of a sample.
+const SINC_ZEROS: f64 = 16.0;
+
+// Exports run on the UI thread, which freezes until they finish. These limits
+// keep one export to around a second in a native release build.
+
+/// Largest number of simulation steps run for one export. The band-limiting
+/// filter costs about as much per step as a short string does.
+pub const MAX_STEPS: usize = 1_000_000;
+
+/// Largest number of mass updates, steps times masses, in one export.
+pub const MAX_MASS_STEPS: usize = 20_000_000;
+
+/// Largest number of audio samples in one export.
+pub const MAX_SAMPLES: usize = 1_000_000;
+
+/// What the pickup measures.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum PickupQuantity {
+    Displacement,
+    Velocity,
+}
+
+impl std::fmt::Display for PickupQuantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PickupQuantity::Displacement => write!(f, "Displacement"),
+            PickupQuantity::Velocity => write!(f, "Velocity"),
+        }
+    }
+}
+
+/// A pickup under the string that turns its motion into audio.
+///
+/// The pickup averages the motion of the masses with Gaussian weights of the
+/// given `width` around `position` (both fractions of the string length); a
+/// width of zero reads the closest mass only. Simulation time is scaled so
+/// that the fundamental of the chain sounds at `pitch` Hz.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone)]
+pub struct Pickup {
+    pub position: f64,
+    pub width: f64,
+    pub quantity: PickupQuantity,
+    pub pitch: f64,
+    pub duration: f64,
+    pub sample_rate: u32,
+    pub bits: u16,
+}
+
+impl Default for Pickup {
+    fn default() -> Self {
+        Self {
+            position: 0.2,
+            width: 0.0,
+            quantity: PickupQuantity::Velocity,
+            pitch: 220.0,
+            duration: 2.0,
+            sample_rate: 44_100,
+            bits: 16,
+        }
+    }
+}
+
+impl Pickup {
+    /// Weight of each of `n` masses in the pickup signal.
+    fn weights(&self, n: usize) -> Vec<f64> {
+        let mut weights = vec![0.0; n];
+        if self.width <= 0.0 {
+            let i = ((self.position * (n + 1) as f64).round() as usize).clamp(1, n) - 1;
+            weights[i] = 1.0;
+        } else {
+            for (i, w) in weights.iter_mut().enumerate() {
+                let x = (i + 1) as f64 / (n + 1) as f64;
+                *w = (-square(x - self.position) / (2.0 * square(self.width))).exp();
+            }
+        }
+        weights
+    }
+
+    /// Simulation time that corresponds to one second of audio.
+    pub fn time_scale(&self, system: &Sys) -> f64 {
+        let fundamental = system.eigenfrequency(1) / (2.0 * PI);
+        self.pitch / fundamental
+    }
+
+    /// Runs a copy of `system` from `time` and returns the normalized pickup
+    /// signal at the audio sample rate. Fails if the string does not move or
+    /// the export would exceed `MAX_STEPS`, `MAX_MASS_STEPS` or `MAX_SAMPLES`.
+    pub fn render(&self, system: &Sys, time: f64, delta: f64) -> Result<Vec<f64>, String> {
+        let at_rest = || "Nothing to export: the string is at rest".to_string();
+        let scale = self.time_scale(system);
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(at_rest());
+        }
+        let count = (self.duration * self.sample_rate as f64).max(0.0);
+        if count > MAX_SAMPLES as f64 {
+            return Err(format!(
+                "The export needs {:.0} samples, more than the limit of {}. Shorten the duration.",
+                count, MAX_SAMPLES
+            ));
+        }
+        let steps = (self.duration * scale / delta).ceil().max(0.0) + 1.0;
+        if steps > MAX_STEPS as f64 {
+            return Err(format!(
+                "The export needs {:.0} simulation steps, more than the limit of {}. \
+                 Shorten the duration, lower the pitch or raise the time-step.",
+                steps, MAX_STEPS
+            ));
+        }
+        let mass_steps = steps * system.len() as f64;
+        if mass_steps > MAX_MASS_STEPS as f64 {
+            return Err(format!(
+                "The export needs {:.0} mass updates ({} masses for {:.0} steps), more than \
+                 the limit of {}. Use fewer masses, shorten the duration, lower the pitch or \
+                 raise the time-step.",
+                mass_steps,
+                system.len(),
+                steps,
+                MAX_MASS_STEPS
+            ));
+        }
+        let steps = steps as usize;
+        let weights = self.weights(system.len());
+        let mut system = system.clone();
+        let mut time = time;
+        let mut signal = Vec::with_capacity(steps);
+        for _ in 0..steps {
+            system.update_system(&mut time, delta);
+            signal.push(
+                weights
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &w)| w != 0.0)
+                    .map(|(i, w)| {
+                        w * match self.quantity {
+                            PickupQuantity::Displacement => system.get_mass_pos(i),
+                            PickupQuantity::Velocity => system.get_mass_vel(i, delta),
+                        }
+                    })
+                    .sum::<f64>(),
+            );
+        }
+        let mean = signal.iter().sum::<f64>() / signal.len() as f64;
+        let samples = resample(
+            &signal.iter().map(|y| y - mean).collect::<Vec<f64>>(),
+            scale / delta,
+            self.sample_rate as f64,
+            count as usize,
+        );
+        let peak = samples.iter().fold(0.0, |p: f64, y| p.max(y.abs()));
+        if peak == 0.0 || !peak.is_finite() {
+            return Err(at_rest());
+        }
+        Ok(samples.iter().map(|y| y / peak).collect())
+    }
+}
+
+/// Band-limited resampling of `signal`, sampled at `from` Hz, to `count`
+/// samples at `to` Hz, with a Hann-windowed sinc that cuts off below the
+/// Nyquist frequency of the lower of the two rates.
+fn resample(signal: &[f64], from: f64, to: f64, count: usize) -> Vec<f64> {
+    let cutoff = (to / from).min(1.0);
+    let half_width = SINC_ZEROS / cutoff;
+    (0..count)
+        .map(|j| {
+            let centre = j as f64 * from / to;
+            let first = (centre - half_width).ceil().max(0.0) as usize;
+            let last = ((centre + half_width).floor() as usize).min(signal.len().saturating_sub(1));
+            (first..=last)
+                .map(|i| {
+                    let x = i as f64 - centre;
+                    let window = 0.5 + 0.5 * (PI * x / half_width).cos();
+                    let arg = PI * cutoff * x;
+                    let sinc = if arg == 0.0 { 1.0 } else { arg.sin() / arg };
+                    signal[i] * cutoff * sinc * window
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Encodes samples in [-1, 1] as a mono PCM WAV file with 16 or 24 bits.
+pub fn wav(samples: &[f64], sample_rate: u32, bits: u16) -> Vec<u8> {
+    let bytes_per_sample = (bits / 8) as u32;
+    let data_len = samples.len() as u32 * bytes_per_sample;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    out.extend_from_slice(&1u16.to_le_bytes()); // mono
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    out.extend_from_slice(&(sample_rate * bytes_per_sample).to_le_bytes());
+    out.extend_from_slice(&(bytes_per_sample as u16).to_le_bytes());
+    out.extend_from_slice(&bits.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    let full_scale = ((1i64 << (bits - 1)) - 1) as f64;
+    for y in samples {
+        let value = (y.clamp(-1.0, 1.0) * full_scale).round() as i32;
+        out.extend_from_slice(&value.to_le_bytes()[..bytes_per_sample as usize]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(bytes: &[u8], i: usize) -> u32 {
+        u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]])
+    }
+
+    fn u16_at(bytes: &[u8], i: usize) -> u16 {
+        u16::from_le_bytes([bytes[i], bytes[i + 1]])
+    }
+
+    #[test]
+    fn wav_header_and_length() {
+        for (bits, bytes_per_sample) in [(16, 2), (24, 3)] {
+            let samples = [0.0, 1.0, -1.0, 0.5, 2.0];
+            let bytes = wav(&samples, 44_100, bits);
+            let data_len = samples.len() * bytes_per_sample;
+            assert_eq!(bytes.len(), 44 + data_len);
+            assert_eq!(&bytes[0..4], b"RIFF");
+            assert_eq!(u32_at(&bytes, 4) as usize, 36 + data_len);
+            assert_eq!(&bytes[8..16], b"WAVEfmt ");
+            assert_eq!(u32_at(&bytes, 16), 16);
+            assert_eq!(u16_at(&bytes, 20), 1);
+            assert_eq!(u16_at(&bytes, 22), 1);
+            assert_eq!(u32_at(&bytes, 24), 44_100);
+            assert_eq!(u32_at(&bytes, 28), 44_100 * bytes_per_sample as u32);
+            assert_eq!(u16_at(&bytes, 32) as usize, bytes_per_sample);
+            assert_eq!(u16_at(&bytes, 34), bits);
+            assert_eq!(&bytes[36..40], b"data");
+            assert_eq!(u32_at(&bytes, 40) as usize, data_len);
+        }
+        // Full scale, clipped, for 16-bit samples.
+        let bytes = wav(&[1.0, -1.0, 2.0], 8_000, 16);
+        assert_eq!(i16::from_le_bytes([bytes[44], bytes[45]]), i16::MAX);
+        assert_eq!(i16::from_le_bytes([bytes[46], bytes[47]]), -i16::MAX);
+        assert_eq!(i16::from_le_bytes([bytes[48], bytes[49]]), i16::MAX);
+    }
+
+    #[test]
+    fn render_length_and_limits() {
+        let pickup = Pickup {
+            duration: 0.1,
+            sample_rate: 8_000,
+            ..Default::default()
+        };
+        let system = Sys::new(3, 10, 1.0);
+        let samples = pickup.render(&system, 0.0, 0.1).unwrap();
+        assert_eq!(samples.len(), 800);
+        assert!(pickup.render(&Sys::new(0, 10, 0.0), 0.0, 0.1).is_err());
+        let long = Pickup {
+            duration: 30.0,
+            pitch: 20_000.0,
+            ..Default::default()
+        };
+        assert!(long.render(&system, 0.0, 0.001).is_err());
+        // Few enough steps, but too many for a long string.
+        let heavy = Pickup {
+            pitch: 5.0,
+            ..Default::default()
+        };
+        let system = Sys::new(3, 500, 1.0);
+        let steps = heavy.duration * heavy.time_scale(&system) / 0.1;
+        assert!(steps < MAX_STEPS as f64);
+        let error = heavy.render(&system, 0.0, 0.1).unwrap_err();
+        assert!(error.contains("mass updates"), "{}", error);
+    }
+}
@@ -0,0 +1,166 @@
+use super::string_dynamics::{check_finite, check_positive, nearest_mass, Sys};
+use std::collections::VecDeque;
+
+/// Number of velocity samples kept for the history plot.
+const HISTORY_LEN: usize = 10_000;
+
+/// A bow drawn across one mass at constant velocity.
+///
+/// The friction force follows a hyperbolic curve that falls from
+/// `static_friction` when the mass sticks to the bow to `dynamic_friction`
+/// when it slips fast, which is what produces Helmholtz (sawtooth) motion.
+/// The chain of masses needs some loss that grows with frequency to settle
+/// into it, such as a light finger damper on the last mass: without one the
+/// ripples left by every stick and slip never die out and the mass chatters.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bow {
+    pub enabled: bool,
+    pub position: f64,
+    pub velocity: f64,
+    pub force: f64,
+    pub static_friction: f64,
+    pub dynamic_friction: f64,
+    pub slip_velocity: f64,
+    sticking: bool,
+}
+
+impl Default for Bow {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            position: 0.125,
+            velocity: 0.2,
+            force: 2.0,
+            static_friction: 0.8,
+            dynamic_friction: 0.3,
+            slip_velocity: 0.1,
+            sticking: false,
+        }
+    }
+}
+
+impl Bow {
+    /// Index of the bowed mass for a string of `n` masses.
+    pub fn target(&self, n: usize) -> usize {
+        nearest_mass(self.position, n)
+    }
+
+    /// Friction coefficient for a given relative velocity between mass and bow.
+    pub fn friction(&self, relative_velocity: f64) -> f64 {
+        self.dynamic_friction
+            + (self.static_friction - self.dynamic_friction)
+                / (1.0 + relative_velocity.abs() / self.slip_velocity)
+    }
+
+    pub fn sticking(&self) -> bool {
+        self.sticking
+    }
+
+    /// Checks that the slip velocity is positive and the other settings finite.
+    pub fn validate(&self) -> Result<(), String> {
+        check_positive("the slip velocity", self.slip_velocity)?;
+        for (what, value) in [
+            ("the bow position", self.position),
+            ("the bow velocity", self.velocity),
+            ("the bow force", self.force),
+            ("the static friction", self.static_friction),
+            ("the dynamic friction", self.dynamic_friction),
+        ] {
+            check_finite(what, value)?;
+        }
+        Ok(())
+    }
+
+    /// Friction force on a mass of `inertia` moving at `mass_velocity` under the
+    /// spring acceleration `accel`. If holding the mass at the bow velocity over the
+    /// next step needs less than the static friction limit, the mass sticks;
+    /// otherwise it slips against the dynamic friction curve.
+    pub fn contact(&mut self, mass_velocity: f64, accel: f64, inertia: f64, delta: f64) -> f64 {
+        if !self.enabled {
+            return 0.0;
+        }
+        let stick_force = inertia * ((self.velocity - mass_velocity) / delta - accel);
+        self.sticking = stick_force.abs() <= self.force * self.static_friction;
+        if self.sticking {
+            return stick_force;
+        }
+        let relative_velocity = mass_velocity - self.velocity;
+        let predicted = mass_velocity + accel * delta - self.velocity;
+        -self.force * self.friction(relative_velocity) * predicted.signum()
+    }
+}
+
+/// Velocity of the bowed mass over time, for the history plot.
+#[derive(Debug, Clone, Default)]
+pub struct BowHistory {
+    samples: VecDeque<[f64; 2]>,
+}
+
+impl BowHistory {
+    pub fn samples(&self) -> &VecDeque<[f64; 2]> {
+        &self.samples
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Appends the velocity of the bowed mass while the bow is enabled. Going
+    /// back in time, as after a reset, starts a new history.
+    pub fn record(&mut self, system: &Sys, time: f64, delta: f64) {
+        if !system.bow.enabled {
+            return;
+        }
+        if self.samples.back().map_or(false, |&[t, _]| time < t) {
+            self.samples.clear();
+        }
+        while self.samples.len() >= HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        let target = system.bow.target(system.len());
+        self.samples
+            .push_back([time, system.get_mass_vel(target, delta)]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn helmholtz_motion() {
+        let size = 30;
+        let delta = 0.05;
+        let mut system = Sys::new(0, size, 0.0);
+        system.bow.enabled = true;
+        system.set_finger(size - 1, 0.3);
+        // The fundamental period of a string with unit tension, spacing and inertia.
+        let period = 2.0 * (size + 1) as f64;
+        let mut time = 0.0;
+        while time < 80.0 * period {
+            system.update_system(&mut time, delta);
+        }
+        let periods = 20.0;
+        let (mut steps, mut sticking, mut flips) = (0, 0, 0);
+        let mut last = system.bow.sticking();
+        while time < (80.0 + periods) * period {
+            system.update_system(&mut time, delta);
+            steps += 1;
+            if system.bow.sticking() {
+                sticking += 1;
+            }
+            if system.bow.sticking() != last {
+                flips += 1;
+            }
+            last = system.bow.sticking();
+        }
+        let flips_per_period = flips as f64 / periods;
+        assert!((1.8..=2.2).contains(&flips_per_period), "{}", flips_per_period);
+        // The mass slips for the fraction of the period the bow position takes up.
+        let beta = (system.bow.target(size) + 1) as f64 / (size + 1) as f64;
+        let stick_fraction = sticking as f64 / steps as f64;
+        assert!((stick_fraction - (1.0 - beta)).abs() < 0.05, "{}", stick_fraction);
+    }
+}
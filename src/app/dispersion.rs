@@ -0,0 +1,51 @@
+use super::transform::{magnitude_spectrum, sine_transform};
+
+/// Angular frequency of a wave with wavenumber `q` (radians per mass spacing)
+/// on a uniform chain of unit masses with the given tension.
+pub fn dispersion(q: f64, tension: f64) -> f64 {
+    2.0 * tension.max(0.0).sqrt() * (0.5 * q).sin()
+}
+
+/// Power in the (wavenumber, frequency) plane of a space-time history, in dB.
+///
+/// Each row of `rows` is the string shape at one time-step. The shape is
+/// first expanded in the sine modes of a string fixed at both ends, then the
+/// time series of every mode is Fourier transformed. Column `k - 1` of the
+/// result holds the spectrum of mode `k`, from zero frequency up to Nyquist.
+///
+/// Only the latest rows are used, so each column has at most `max_bins` bins.
+pub fn space_time_spectrum(rows: &[&Vec<f64>], max_bins: usize) -> Vec<Vec<f64>> {
+    // A transform of `size` rows has `size / 2 + 1` bins.
+    let max_rows = (2 * max_bins.max(2) - 1).next_power_of_two() / 2;
+    let rows = &rows[rows.len().saturating_sub(max_rows)..];
+    let n = rows.first().map_or(0, |row| row.len());
+    let size = rows.len().next_power_of_two();
+    let modes: Vec<Vec<f64>> = rows.iter().map(|row| sine_transform(row)).collect();
+    (0..n)
+        .map(|k| {
+            let series: Vec<f64> = modes.iter().map(|m| m[k]).collect();
+            magnitude_spectrum(&series, size)
+                .into_iter()
+                .map(|m| 20.0 * (m + 1e-12).log10())
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bins_are_limited() {
+        let rows: Vec<Vec<f64>> = (0..1000).map(|t| vec![(0.3 * t as f64).sin(); 4]).collect();
+        let rows: Vec<&Vec<f64>> = rows.iter().collect();
+        for max_bins in [2, 100, 513, 1024] {
+            let columns = space_time_spectrum(&rows, max_bins);
+            assert_eq!(columns.len(), 4);
+            assert!(columns[0].len() <= max_bins, "{}", max_bins);
+        }
+        assert_eq!(space_time_spectrum(&rows, 513)[0].len(), 513);
+        assert_eq!(space_time_spectrum(&rows, 8192)[0].len(), 513);
+    }
+}
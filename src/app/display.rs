@@ -3,9 +3,30 @@ use egui::Color32;
 const GREEN: Color32 = Color32::from_rgb(100, 200, 100);
 const RED: Color32 = Color32::from_rgb(255, 0, 0);
 
+/// What clicking and dragging in the central plot does.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum PlotTool {
+    /// Pan the view and click masses to pin or release them.
+    Pin,
+    /// Grab a mass and move it with the mouse.
+    Drag,
+    /// Draw a freehand curve that becomes the new initial shape.
+    Draw,
+}
+
+impl std::fmt::Display for PlotTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlotTool::Pin => write!(f, "Pan and pin"),
+            PlotTool::Drag => write!(f, "Drag masses"),
+            PlotTool::Draw => write!(f, "Draw shape"),
+        }
+    }
+}
+
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct DisplaySettings {
     pub radius: f32,
     pub harmonic_value: i32,
@@ -13,6 +34,14 @@ pub struct DisplaySettings {
     pub windowed: bool,
     pub boundary_style: String,
     pub width: f64,
+    pub plot_tool: PlotTool,
+    pub show_spectrum: bool,
+    pub spectrum_log: bool,
+    pub show_probes: bool,
+    pub show_spectrogram: bool,
+    pub show_phase: bool,
+    pub show_kymograph: bool,
+    pub show_dispersion: bool,
 }
 
 impl Default for DisplaySettings {
@@ -24,6 +53,14 @@ impl Default for DisplaySettings {
             windowed: false,
             boundary_style: "line".to_string(),
             width: 1.0,
+            plot_tool: PlotTool::Pin,
+            show_spectrum: false,
+            spectrum_log: false,
+            show_probes: true,
+            show_spectrogram: false,
+            show_phase: false,
+            show_kymograph: false,
+            show_dispersion: false,
         }
     }
 }
@@ -0,0 +1,26 @@
+/// Writes `contents` to `path` and returns a status message for the UI.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file(path: &str, contents: &[u8]) -> String {
+    match std::fs::write(path, contents) {
+        Ok(()) => format!("Saved {}", path),
+        Err(error) => format!("Could not save {}: {}", path, error),
+    }
+}
+
+/// The web app has no file system to write to.
+#[cfg(target_arch = "wasm32")]
+pub fn save_file(path: &str, _contents: &[u8]) -> String {
+    format!("Could not save {}: not supported in the web app", path)
+}
+
+/// Reads the text file at `path`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_file(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|error| error.to_string())
+}
+
+/// The web app has no file system to read from.
+#[cfg(target_arch = "wasm32")]
+pub fn load_file(_path: &str) -> Result<String, String> {
+    Err("not supported in the web app".to_string())
+}
@@ -0,0 +1,533 @@
+use std::f64::consts::{E, PI};
+
+/// A parsed mathematical expression of a single variable `x`.
+///
+/// Supports `+ - * / ^`, comparisons (`< <= > >= == !=`, which give 1 or 0),
+/// the constants `pi` and `e`, and the functions `sin cos tan asin acos atan
+/// sinh cosh tanh exp ln log sqrt abs sign floor ceil min max pow if`.
+/// `if(cond, a, b)` picks `a` when `cond` is non-zero, which allows
+/// piecewise definitions such as `if(x < 0.5, 2*x, 2 - 2*x)`.
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Number(f64),
+    Variable,
+    Negate(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Expr>),
+}
+
+/// Binary operators, from the loosest binding to the tightest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOp {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+impl BinOp {
+    fn from_token(token: &str) -> Option<BinOp> {
+        match token {
+            "<" => Some(BinOp::Less),
+            "<=" => Some(BinOp::LessEqual),
+            ">" => Some(BinOp::Greater),
+            ">=" => Some(BinOp::GreaterEqual),
+            "==" => Some(BinOp::Equal),
+            "!=" => Some(BinOp::NotEqual),
+            "+" => Some(BinOp::Add),
+            "-" => Some(BinOp::Subtract),
+            "*" => Some(BinOp::Multiply),
+            "/" => Some(BinOp::Divide),
+            "^" => Some(BinOp::Power),
+            _ => None,
+        }
+    }
+
+    fn apply(self, l: f64, r: f64) -> f64 {
+        match self {
+            BinOp::Less => (l < r) as i32 as f64,
+            BinOp::LessEqual => (l <= r) as i32 as f64,
+            BinOp::Greater => (l > r) as i32 as f64,
+            BinOp::GreaterEqual => (l >= r) as i32 as f64,
+            BinOp::Equal => (l == r) as i32 as f64,
+            BinOp::NotEqual => (l != r) as i32 as f64,
+            BinOp::Add => l + r,
+            BinOp::Subtract => l - r,
+            BinOp::Multiply => l * r,
+            BinOp::Divide => l / r,
+            BinOp::Power => l.powf(r),
+        }
+    }
+}
+
+/// Supported functions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Func {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Exp,
+    Ln,
+    Log,
+    Sqrt,
+    Abs,
+    Sign,
+    Floor,
+    Ceil,
+    Min,
+    Max,
+    Pow,
+    If,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Func> {
+        match name {
+            "sin" => Some(Func::Sin),
+            "cos" => Some(Func::Cos),
+            "tan" => Some(Func::Tan),
+            "asin" => Some(Func::Asin),
+            "acos" => Some(Func::Acos),
+            "atan" => Some(Func::Atan),
+            "sinh" => Some(Func::Sinh),
+            "cosh" => Some(Func::Cosh),
+            "tanh" => Some(Func::Tanh),
+            "exp" => Some(Func::Exp),
+            "ln" => Some(Func::Ln),
+            "log" => Some(Func::Log),
+            "sqrt" => Some(Func::Sqrt),
+            "abs" => Some(Func::Abs),
+            "sign" => Some(Func::Sign),
+            "floor" => Some(Func::Floor),
+            "ceil" => Some(Func::Ceil),
+            "min" => Some(Func::Min),
+            "max" => Some(Func::Max),
+            "pow" => Some(Func::Pow),
+            "if" => Some(Func::If),
+            _ => None,
+        }
+    }
+
+    /// Number of arguments the function takes.
+    fn arity(self) -> usize {
+        match self {
+            Func::Sin
+            | Func::Cos
+            | Func::Tan
+            | Func::Asin
+            | Func::Acos
+            | Func::Atan
+            | Func::Sinh
+            | Func::Cosh
+            | Func::Tanh
+            | Func::Exp
+            | Func::Ln
+            | Func::Log
+            | Func::Sqrt
+            | Func::Abs
+            | Func::Sign
+            | Func::Floor
+            | Func::Ceil => 1,
+            Func::Min | Func::Max | Func::Pow => 2,
+            Func::If => 3,
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Expr, String> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            index: 0,
+        };
+        let expr = parser.comparison()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected '{}'", token)),
+        }
+    }
+
+    pub fn eval(&self, x: f64) -> f64 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Variable => x,
+            Expr::Negate(expr) => -expr.eval(x),
+            Expr::Binary(op, l, r) => op.apply(l.eval(x), r.eval(x)),
+            Expr::Call(func, args) => {
+                let a = |i: usize| args[i].eval(x);
+                match func {
+                    Func::Sin => a(0).sin(),
+                    Func::Cos => a(0).cos(),
+                    Func::Tan => a(0).tan(),
+                    Func::Asin => a(0).asin(),
+                    Func::Acos => a(0).acos(),
+                    Func::Atan => a(0).atan(),
+                    Func::Sinh => a(0).sinh(),
+                    Func::Cosh => a(0).cosh(),
+                    Func::Tanh => a(0).tanh(),
+                    Func::Exp => a(0).exp(),
+                    Func::Ln => a(0).ln(),
+                    Func::Log => a(0).log10(),
+                    Func::Sqrt => a(0).sqrt(),
+                    Func::Abs => a(0).abs(),
+                    Func::Sign => {
+                        let v = a(0);
+                        if v == 0.0 {
+                            0.0
+                        } else {
+                            v.signum()
+                        }
+                    }
+                    Func::Floor => a(0).floor(),
+                    Func::Ceil => a(0).ceil(),
+                    Func::Min => a(0).min(a(1)),
+                    Func::Max => a(0).max(a(1)),
+                    Func::Pow => a(0).powf(a(1)),
+                    // Only the chosen branch is evaluated.
+                    Func::If => {
+                        if a(0) != 0.0 {
+                            a(1)
+                        } else {
+                            a(2)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<String>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Exponent, as in 1e-3.
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else if "<>=!".contains(c) {
+            if i + 1 < chars.len() && chars[i + 1] == '=' {
+                tokens.push(format!("{}=", c));
+                i += 2;
+            } else if c == '<' || c == '>' {
+                tokens.push(c.to_string());
+                i += 1;
+            } else {
+                return Err(format!("unexpected '{}'", c));
+            }
+        } else if "+-*/^(),".contains(c) {
+            tokens.push(c.to_string());
+            i += 1;
+        } else {
+            return Err(format!("unexpected '{}'", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.index).map(|t| t.as_str())
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected '{}' but found '{}'", expected, token)),
+            None => Err(format!("expected '{}'", expected)),
+        }
+    }
+
+    /// Consumes the next token if it is one of the operators in `ops`.
+    fn operator(&mut self, ops: &[BinOp]) -> Option<BinOp> {
+        let op = self
+            .peek()
+            .and_then(BinOp::from_token)
+            .filter(|op| ops.contains(op))?;
+        self.index += 1;
+        Some(op)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let mut expr = self.sum()?;
+        while let Some(op) = self.operator(&[
+            BinOp::Less,
+            BinOp::LessEqual,
+            BinOp::Greater,
+            BinOp::GreaterEqual,
+            BinOp::Equal,
+            BinOp::NotEqual,
+        ]) {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.sum()?));
+        }
+        Ok(expr)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        while let Some(op) = self.operator(&[BinOp::Add, BinOp::Subtract]) {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while let Some(op) = self.operator(&[BinOp::Multiply, BinOp::Divide]) {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some("-") => {
+                self.next();
+                Ok(Expr::Negate(Box::new(self.unary()?)))
+            }
+            Some("+") => {
+                self.next();
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// Exponentiation binds tighter than unary minus and is right associative.
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if let Some(op) = self.operator(&[BinOp::Power]) {
+            let exponent = self.unary()?;
+            return Ok(Expr::Binary(op, Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self.next().ok_or("unexpected end of expression")?;
+        if token == "(" {
+            let expr = self.comparison()?;
+            self.expect(")")?;
+            return Ok(expr);
+        }
+        if let Ok(value) = token.parse::<f64>() {
+            return Ok(Expr::Number(value));
+        }
+        match token.as_str() {
+            "x" => Ok(Expr::Variable),
+            "pi" => Ok(Expr::Number(PI)),
+            "e" => Ok(Expr::Number(E)),
+            name => {
+                let func = Func::from_name(name).ok_or(format!("unknown name '{}'", name))?;
+                let expected = func.arity();
+                self.expect("(")?;
+                let mut args = vec![self.comparison()?];
+                while self.peek() == Some(",") {
+                    self.next();
+                    args.push(self.comparison()?);
+                }
+                self.expect(")")?;
+                if args.len() != expected {
+                    return Err(format!(
+                        "'{}' takes {} argument(s) but got {}",
+                        name,
+                        expected,
+                        args.len()
+                    ));
+                }
+                Ok(Expr::Call(func, args))
+            }
+        }
+    }
+}
+
+/// A pair of initial displacement and velocity expressions saved under a name.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedExpression {
+    pub name: String,
+    pub displacement: String,
+    pub velocity: String,
+}
+
+impl Default for NamedExpression {
+    fn default() -> Self {
+        Self {
+            name: "Fundamental".to_string(),
+            displacement: "sin(pi*x)".to_string(),
+            velocity: "0".to_string(),
+        }
+    }
+}
+
+impl NamedExpression {
+    /// Samples both expressions at the positions of `n` masses, with the
+    /// boundaries at x = 0 and x = 1. Infinite or undefined values are an
+    /// error, as they would spread through the whole simulation.
+    pub fn sample(&self, n: usize) -> Result<(Vec<f64>, Vec<f64>), String> {
+        let f = Expr::parse(&self.displacement).map_err(|e| format!("f(x): {}", e))?;
+        let g = Expr::parse(&self.velocity).map_err(|e| format!("g(x): {}", e))?;
+        let xs: Vec<f64> = (0..n)
+            .map(|i| (i as f64 + 1.0) / ((n + 1) as f64))
+            .collect();
+        let sample = |expr: &Expr, name: &str| {
+            xs.iter()
+                .map(|&x| {
+                    let value = expr.eval(x);
+                    if value.is_finite() {
+                        Ok(value)
+                    } else {
+                        Err(format!("{}: {} at x = {:.3}", name, value, x))
+                    }
+                })
+                .collect::<Result<Vec<f64>, String>>()
+        };
+        Ok((sample(&f, "f(x)")?, sample(&g, "g(x)")?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, x: f64) -> f64 {
+        Expr::parse(source).unwrap().eval(x)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3", 0.0), 7.0);
+        assert_eq!(eval("(1 + 2) * 3", 0.0), 9.0);
+        assert_eq!(eval("8 / 4 / 2", 0.0), 1.0);
+        assert_eq!(eval("10 - 4 - 3", 0.0), 3.0);
+        assert_eq!(eval("2 ^ 3 ^ 2", 0.0), 512.0);
+        assert_eq!(eval("1 + 1 < 3", 0.0), 1.0);
+        assert_eq!(eval("x * 2 >= 1", 0.5), 1.0);
+        assert_eq!(eval("1e-3 * 1E3", 0.0), 1.0);
+    }
+
+    #[test]
+    fn comparisons() {
+        for (op, results) in [
+            ("<", [1.0, 0.0, 0.0]),
+            ("<=", [1.0, 1.0, 0.0]),
+            (">", [0.0, 0.0, 1.0]),
+            (">=", [0.0, 1.0, 1.0]),
+            ("==", [0.0, 1.0, 0.0]),
+            ("!=", [1.0, 0.0, 1.0]),
+        ] {
+            let expr = Expr::parse(&format!("x {} 1", op)).unwrap();
+            for (x, result) in [0.0, 1.0, 2.0].into_iter().zip(results) {
+                assert_eq!(expr.eval(x), result, "{} {} 1", x, op);
+            }
+        }
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(eval("-2 ^ 2", 0.0), -4.0);
+        assert_eq!(eval("2 ^ -1", 0.0), 0.5);
+        assert_eq!(eval("--x", 3.0), 3.0);
+        assert_eq!(eval("-x * 2", 3.0), -6.0);
+        assert_eq!(eval("+x", 3.0), 3.0);
+    }
+
+    #[test]
+    fn functions() {
+        assert!((eval("sin(pi*x)", 0.5) - 1.0).abs() < 1e-15);
+        assert_eq!(eval("ln(e)", 0.0), 1.0);
+        assert_eq!(eval("max(x, 1 - x)", 0.25), 0.75);
+        assert_eq!(eval("pow(2, 10)", 0.0), 1024.0);
+        assert_eq!(eval("sign(x - 0.5)", 0.5), 0.0);
+        assert_eq!(eval("if(x < 0.5, 2*x, 2 - 2*x)", 0.75), 0.5);
+    }
+
+    #[test]
+    fn errors() {
+        for (source, error) in [
+            ("", "unexpected end of expression"),
+            ("1 +", "unexpected end of expression"),
+            ("(1 + 2", "expected ')'"),
+            ("1 2", "unexpected '2'"),
+            ("y", "unknown name 'y'"),
+            ("sin x", "expected '(' but found 'x'"),
+            ("min(1)", "'min' takes 2 argument(s) but got 1"),
+            ("1 = 2", "unexpected '='"),
+            ("2 $ 3", "unexpected '$'"),
+        ] {
+            assert_eq!(Expr::parse(source).unwrap_err(), error, "{}", source);
+        }
+    }
+
+    #[test]
+    fn non_finite_samples() {
+        for (displacement, velocity) in
+            [("1/(x-0.5)", "0"), ("ln(x - 0.5)", "0"), ("0", "sqrt(-1)")]
+        {
+            let expression = NamedExpression {
+                displacement: displacement.to_string(),
+                velocity: velocity.to_string(),
+                ..Default::default()
+            };
+            assert!(
+                expression.sample(3).is_err(),
+                "{} {}",
+                displacement,
+                velocity
+            );
+        }
+        let (shape, velocity) = NamedExpression::default().sample(3).unwrap();
+        assert_eq!(shape.len(), 3);
+        assert_eq!(velocity, vec![0.0; 3]);
+    }
+}
@@ -0,0 +1,604 @@
+use super::display::DisplaySettings;
+use super::obstacle::ObstacleShape;
+use super::raster::{Align, Canvas};
+use super::string_dynamics::Sys;
+use egui::Color32;
+use image::RgbaImage;
+use std::fmt::Write;
+
+/// Background of exported images, matching the dark plot background.
+pub const BACKGROUND: Color32 = Color32::from_rgb(27, 27, 27);
+const GRID: Color32 = Color32::from_rgb(55, 55, 55);
+const LABEL: Color32 = Color32::from_rgb(160, 160, 160);
+/// Height of axis labels in points.
+const LABEL_SIZE: f64 = 10.0;
+
+/// Text at a position in points, with its alignment.
+type Label = ([f64; 2], String, Align);
+
+/// Shape of point markers, as in the central plot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Marker {
+    Circle,
+    Diamond,
+    Square,
+    Up,
+}
+
+/// Something drawn in a figure, in data coordinates. Widths and radii are in
+/// points, so they do not change with the zoom.
+#[derive(Debug, Clone)]
+pub enum Mark {
+    Line {
+        points: Vec<[f64; 2]>,
+        width: f64,
+        color: Color32,
+    },
+    Markers {
+        points: Vec<[f64; 2]>,
+        shape: Marker,
+        radius: f64,
+        filled: bool,
+        color: Color32,
+    },
+    VLine {
+        x: f64,
+        width: f64,
+        color: Color32,
+    },
+}
+
+/// A plot that can be drawn without a GPU, either to an image or to SVG.
+#[derive(Debug, Clone)]
+pub struct Figure {
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+    pub marks: Vec<Mark>,
+    pub axes: bool,
+}
+
+impl Figure {
+    /// Largest distance of any mark from the horizontal axis.
+    pub fn extent(&self) -> f64 {
+        self.marks
+            .iter()
+            .flat_map(|mark| match mark {
+                Mark::Line { points, .. } | Mark::Markers { points, .. } => points.as_slice(),
+                Mark::VLine { .. } => &[],
+            })
+            .map(|p| p[1].abs())
+            .filter(|y| y.is_finite())
+            .fold(0.0, f64::max)
+    }
+
+    /// Widens whichever range is too narrow for one unit to take the same
+    /// length on both axes of a `width` by `height` figure.
+    pub fn equal_aspect(&mut self, width: f64, height: f64) {
+        let x_span = self.x_range.1 - self.x_range.0;
+        let y_span = self.y_range.1 - self.y_range.0;
+        if x_span / width > y_span / height {
+            let extra = 0.5 * (x_span * height / width - y_span);
+            self.y_range = (self.y_range.0 - extra, self.y_range.1 + extra);
+        } else {
+            let extra = 0.5 * (y_span * width / height - x_span);
+            self.x_range = (self.x_range.0 - extra, self.x_range.1 + extra);
+        }
+    }
+
+    /// Position of the data point `p` in a `width` by `height` figure, in
+    /// points from the top left corner.
+    fn to_points(&self, p: [f64; 2], width: f64, height: f64) -> [f64; 2] {
+        [
+            (p[0] - self.x_range.0) / (self.x_range.1 - self.x_range.0) * width,
+            (self.y_range.1 - p[1]) / (self.y_range.1 - self.y_range.0) * height,
+        ]
+    }
+
+    /// Grid lines and their labels, in points.
+    fn grid(&self, width: f64, height: f64) -> (Vec<[[f64; 2]; 2]>, Vec<Label>) {
+        let mut lines = Vec::new();
+        let mut labels = Vec::new();
+        for (value, text) in ticks(self.x_range, width / 80.0) {
+            let x = self.to_points([value, 0.0], width, height)[0];
+            lines.push([[x, 0.0], [x, height]]);
+            // Leave room for the labels of the vertical axis.
+            if x > 4.0 * LABEL_SIZE {
+                labels.push(([x, height - LABEL_SIZE], text, Align::Centre));
+            }
+        }
+        for (value, text) in ticks(self.y_range, height / 60.0) {
+            let y = self.to_points([0.0, value], width, height)[1];
+            lines.push([[0.0, y], [width, y]]);
+            if y > LABEL_SIZE && y < height - 2.0 * LABEL_SIZE {
+                labels.push(([4.0, y], text, Align::Left));
+            }
+        }
+        (lines, labels)
+    }
+
+    /// Draws the figure at `width` by `height` points with `scale` pixels per
+    /// point.
+    pub fn raster(&self, width: u32, height: u32, scale: f64) -> RgbaImage {
+        let (w, h) = (width as f64, height as f64);
+        let pixels = |p: [f64; 2]| [p[0] * scale, p[1] * scale];
+        let mut canvas = Canvas::new(
+            (w * scale).round() as u32,
+            (h * scale).round() as u32,
+            BACKGROUND,
+        );
+        let grid = self.axes.then(|| self.grid(w, h));
+        if let Some((lines, _)) = &grid {
+            for line in lines {
+                canvas.polyline(&[pixels(line[0]), pixels(line[1])], scale, GRID);
+            }
+        }
+        for mark in &self.marks {
+            match mark {
+                Mark::Line {
+                    points,
+                    width,
+                    color,
+                } => {
+                    let points: Vec<[f64; 2]> = points
+                        .iter()
+                        .map(|&p| pixels(self.to_points(p, w, h)))
+                        .collect();
+                    canvas.polyline(&points, width * scale, *color);
+                }
+                Mark::Markers {
+                    points,
+                    shape,
+                    radius,
+                    filled,
+                    color,
+                } => {
+                    for &p in points {
+                        let centre = pixels(self.to_points(p, w, h));
+                        let radius = radius * scale;
+                        if *shape == Marker::Circle && *filled {
+                            canvas.circle(centre, radius, *color);
+                            continue;
+                        }
+                        let corners = corners(*shape, centre, radius);
+                        if *filled {
+                            canvas.polygon(&corners, *color);
+                        } else {
+                            let mut outline = corners.clone();
+                            outline.push(corners[0]);
+                            canvas.polyline(&outline, scale, *color);
+                        }
+                    }
+                }
+                Mark::VLine { x, width, color } => {
+                    let x = self.to_points([*x, 0.0], w, h)[0];
+                    canvas.polyline(&[pixels([x, 0.0]), pixels([x, h])], width * scale, *color);
+                }
+            }
+        }
+        if let Some((_, labels)) = &grid {
+            for (position, text, align) in labels {
+                canvas.text(pixels(*position), text, LABEL_SIZE * scale, LABEL, *align);
+            }
+        }
+        canvas.into_image()
+    }
+
+    /// The figure as an SVG document of `width` by `height` points.
+    pub fn svg(&self, width: u32, height: u32) -> String {
+        let (w, h) = (width as f64, height as f64);
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+            width, height
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            svg_color(BACKGROUND).0
+        );
+        let grid = self.axes.then(|| self.grid(w, h));
+        if let Some((lines, _)) = &grid {
+            for line in lines {
+                let _ = writeln!(
+                    svg,
+                    r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" {}/>"#,
+                    line[0][0],
+                    line[0][1],
+                    line[1][0],
+                    line[1][1],
+                    stroke(GRID, 1.0)
+                );
+            }
+        }
+        // Clip marks to the plot, as the central plot does.
+        let _ = writeln!(
+            svg,
+            r#"<clipPath id="plot"><rect width="{}" height="{}"/></clipPath>"#,
+            width, height
+        );
+        let _ = writeln!(svg, r#"<g clip-path="url(#plot)">"#);
+        for mark in &self.marks {
+            match mark {
+                Mark::Line {
+                    points,
+                    width,
+                    color,
+                } => {
+                    let points: Vec<[f64; 2]> =
+                        points.iter().map(|&p| self.to_points(p, w, h)).collect();
+                    let _ = writeln!(
+                        svg,
+                        r#"<polyline points="{}" fill="none" {} stroke-linejoin="round"/>"#,
+                        svg_points(&points),
+                        stroke(*color, *width)
+                    );
+                }
+                Mark::Markers {
+                    points,
+                    shape,
+                    radius,
+                    filled,
+                    color,
+                } => {
+                    let paint = if *filled {
+                        fill(*color)
+                    } else {
+                        format!(r#"fill="none" {}"#, stroke(*color, 1.0))
+                    };
+                    for &p in points {
+                        let centre = self.to_points(p, w, h);
+                        if *shape == Marker::Circle {
+                            let _ = writeln!(
+                                svg,
+                                r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" {}/>"#,
+                                centre[0], centre[1], radius, paint
+                            );
+                        } else {
+                            let _ = writeln!(
+                                svg,
+                                r#"<polygon points="{}" {}/>"#,
+                                svg_points(&corners(*shape, centre, *radius)),
+                                paint
+                            );
+                        }
+                    }
+                }
+                Mark::VLine { x, width, color } => {
+                    let x = self.to_points([*x, 0.0], w, h)[0];
+                    let _ = writeln!(
+                        svg,
+                        r#"<line x1="{0:.2}" y1="0" x2="{0:.2}" y2="{1}" {2}/>"#,
+                        x,
+                        height,
+                        stroke(*color, *width)
+                    );
+                }
+            }
+        }
+        let _ = writeln!(svg, "</g>");
+        if let Some((_, labels)) = &grid {
+            for (position, text, align) in labels {
+                let anchor = match align {
+                    Align::Left => "start",
+                    Align::Centre => "middle",
+                };
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="{:.2}" y="{:.2}" {} font-family="sans-serif" font-size="{}" text-anchor="{}" dominant-baseline="middle">{}</text>"#,
+                    position[0],
+                    position[1],
+                    fill(LABEL),
+                    LABEL_SIZE,
+                    anchor,
+                    text
+                );
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// Corners of a marker polygon around `centre`, in the same units as `radius`.
+fn corners(shape: Marker, centre: [f64; 2], radius: f64) -> Vec<[f64; 2]> {
+    let [x, y] = centre;
+    match shape {
+        Marker::Circle => (0..32)
+            .map(|i| {
+                let angle = i as f64 * std::f64::consts::TAU / 32.0;
+                [x + radius * angle.cos(), y + radius * angle.sin()]
+            })
+            .collect(),
+        Marker::Diamond => vec![
+            [x, y - radius],
+            [x + radius, y],
+            [x, y + radius],
+            [x - radius, y],
+        ],
+        Marker::Square => {
+            let r = radius * std::f64::consts::FRAC_1_SQRT_2;
+            vec![
+                [x - r, y - r],
+                [x + r, y - r],
+                [x + r, y + r],
+                [x - r, y + r],
+            ]
+        }
+        Marker::Up => {
+            let (sin, cos) = (0.5 * radius, 0.75f64.sqrt() * radius);
+            vec![[x, y - radius], [x + cos, y + sin], [x - cos, y + sin]]
+        }
+    }
+}
+
+/// Evenly spaced round values in `range`, about `count` of them, with labels
+/// that show just enough decimals.
+fn ticks(range: (f64, f64), count: f64) -> Vec<(f64, String)> {
+    let span = range.1 - range.0;
+    if !(span > 0.0 && span.is_finite()) {
+        return Vec::new();
+    }
+    let raw = span / count.max(1.0);
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|&s| s >= raw)
+        .unwrap_or(10.0 * magnitude);
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let first = (range.0 / step).ceil() as i64;
+    let last = (range.1 / step).floor() as i64;
+    (first..=last)
+        .map(|k| {
+            let value = k as f64 * step;
+            let text = format!("{:.*}", decimals, value);
+            // Avoid labelling the origin as "-0".
+            let text = if k == 0 {
+                format!("{:.*}", decimals, 0.0)
+            } else {
+                text
+            };
+            (value, text)
+        })
+        .collect()
+}
+
+/// Hex colour and opacity of a premultiplied colour.
+fn svg_color(color: Color32) -> (String, f64) {
+    let alpha = color.a();
+    let unmultiplied = |c: u8| {
+        if alpha == 0 {
+            0
+        } else {
+            (c as u32 * 255 / alpha as u32).min(255)
+        }
+    };
+    (
+        format!(
+            "#{:02x}{:02x}{:02x}",
+            unmultiplied(color.r()),
+            unmultiplied(color.g()),
+            unmultiplied(color.b())
+        ),
+        alpha as f64 / 255.0,
+    )
+}
+
+fn fill(color: Color32) -> String {
+    let (hex, opacity) = svg_color(color);
+    format!(r#"fill="{}" fill-opacity="{:.3}""#, hex, opacity)
+}
+
+fn stroke(color: Color32, width: f64) -> String {
+    let (hex, opacity) = svg_color(color);
+    format!(
+        r#"stroke="{}" stroke-opacity="{:.3}" stroke-width="{}""#,
+        hex, opacity, width
+    )
+}
+
+fn svg_points(points: &[[f64; 2]]) -> String {
+    points
+        .iter()
+        .filter(|p| p[0].is_finite() && p[1].is_finite())
+        .map(|p| format!("{:.2},{:.2}", p[0], p[1]))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Positions of the boundaries and masses of `system` in plot coordinates, as
+/// drawn by the central plot.
+pub fn string_points(system: &Sys, width: f64) -> Vec<[f64; 2]> {
+    let n = system.len();
+    (0..n + 2)
+        .map(|i| {
+            if i == 0 || i == n + 1 {
+                [i as f64 / (n + 1) as f64 * width, 0.0]
+            } else {
+                [
+                    (i as f64 + system.get_mass_long_pos(i - 1)) / (n + 1) as f64 * width,
+                    system.get_mass_pos(i - 1),
+                ]
+            }
+        })
+        .collect()
+}
+
+/// The string with its masses, pins, obstacles, hammer and boundaries, in the
+/// colours of the central plot and scaled vertically to fit.
+pub fn string_figure(system: &Sys, display: &DisplaySettings) -> Figure {
+    let width = display.width;
+    let colors = &display.display_colors;
+    let radius = display.radius as f64;
+    let n = system.len();
+    let points = string_points(system, width);
+    let mut marks = vec![
+        Mark::Line {
+            points: points.clone(),
+            width: 1.5,
+            color: colors[1],
+        },
+        Mark::Markers {
+            points: points[1..n + 1].to_vec(),
+            shape: Marker::Circle,
+            radius,
+            filled: true,
+            color: colors[2],
+        },
+        Mark::Markers {
+            points: (0..n)
+                .filter(|&i| system.is_pinned(i))
+                .map(|i| points[i + 1])
+                .collect(),
+            shape: Marker::Diamond,
+            radius: radius * 1.5,
+            filled: false,
+            color: colors[0],
+        },
+    ];
+    for obstacle in &system.obstacles {
+        let outline: Vec<[f64; 2]> = obstacle
+            .outline()
+            .iter()
+            .map(|&[x, y]| [x * width, y])
+            .collect();
+        marks.push(if obstacle.shape == ObstacleShape::Fret {
+            Mark::Markers {
+                points: outline,
+                shape: Marker::Up,
+                radius,
+                filled: true,
+                color: colors[0],
+            }
+        } else {
+            Mark::Line {
+                points: outline,
+                width: 2.0,
+                color: colors[0],
+            }
+        });
+    }
+    if system.hammer.in_flight() {
+        let target = system.hammer.target(n);
+        marks.push(Mark::Markers {
+            points: vec![[
+                (target as f64 + 1.0) / (n + 1) as f64 * width,
+                system.hammer.get_pos(),
+            ]],
+            shape: Marker::Square,
+            radius: radius * 1.5,
+            filled: true,
+            color: colors[0],
+        });
+    }
+    if display.boundary_style == "line" {
+        marks.push(Mark::VLine {
+            x: 0.0,
+            width: 1.0,
+            color: colors[0],
+        });
+        marks.push(Mark::VLine {
+            x: width,
+            width: 1.0,
+            color: colors[0],
+        });
+    } else if display.boundary_style == "mass" {
+        marks.push(Mark::Markers {
+            points: vec![[0.0, 0.0], [width, 0.0]],
+            shape: Marker::Circle,
+            radius: 4.0,
+            filled: true,
+            color: colors[0],
+        });
+    }
+    let margin = 0.05 * width;
+    let mut figure = Figure {
+        x_range: (-margin, width + margin),
+        y_range: (-1.0, 1.0),
+        marks,
+        axes: false,
+    };
+    let extent = figure.extent();
+    if extent > 0.0 {
+        figure.y_range = (-1.1 * extent, 1.1 * extent);
+    }
+    figure
+}
+
+/// File format of exported plots.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum PlotFormat {
+    Svg,
+    Png,
+}
+
+impl std::fmt::Display for PlotFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlotFormat::Svg => write!(f, "SVG"),
+            PlotFormat::Png => write!(f, "PNG"),
+        }
+    }
+}
+
+impl PlotFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PlotFormat::Svg => "svg",
+            PlotFormat::Png => "png",
+        }
+    }
+}
+
+/// Settings for exporting the current frame of the string. `width` and
+/// `height` are in points; PNG images have `scale` pixels per point.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone)]
+pub struct PlotExport {
+    pub format: PlotFormat,
+    pub width: u32,
+    pub height: u32,
+    pub scale: f64,
+    pub axes: bool,
+    pub equal_aspect: bool,
+}
+
+impl Default for PlotExport {
+    fn default() -> Self {
+        Self {
+            format: PlotFormat::Svg,
+            width: 640,
+            height: 360,
+            scale: 2.0,
+            axes: true,
+            equal_aspect: true,
+        }
+    }
+}
+
+impl PlotExport {
+    /// The current frame of `system`, encoded in the chosen format.
+    pub fn render(&self, system: &Sys, display: &DisplaySettings) -> Result<Vec<u8>, String> {
+        let mut figure = string_figure(system, display);
+        figure.axes = self.axes;
+        if self.equal_aspect {
+            figure.equal_aspect(self.width as f64, self.height as f64);
+        }
+        match self.format {
+            PlotFormat::Svg => Ok(figure.svg(self.width, self.height).into_bytes()),
+            PlotFormat::Png => {
+                let image = figure.raster(self.width, self.height, self.scale);
+                let mut bytes = Vec::new();
+                image::DynamicImage::ImageRgba8(image)
+                    .write_to(
+                        &mut std::io::Cursor::new(&mut bytes),
+                        image::ImageOutputFormat::Png,
+                    )
+                    .map_err(|e| format!("Could not encode image: {}", e))?;
+                Ok(bytes)
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use super::transform::{magnitude_spectrum, peaks};
+use std::f64::consts::PI;
+
+/// Zero-padding factor used for peak estimation.
+const PADDING: usize = 4;
+
+/// A spectral peak of a time series, as an angular frequency.
+#[derive(Debug, Clone, Copy)]
+pub struct Peak {
+    pub frequency: f64,
+    pub amplitude: f64,
+}
+
+/// Estimates the `count` strongest angular frequencies in `samples`, taken
+/// every `delta`. Each peak of the windowed, zero-padded spectrum is refined
+/// by fitting a parabola to the log magnitudes of its bin and its neighbours.
+pub fn estimate_peaks(samples: &[f64], delta: f64, count: usize) -> Vec<Peak> {
+    if samples.len() < 4 {
+        return Vec::new();
+    }
+    let size = (samples.len() * PADDING).next_power_of_two();
+    let spectrum = magnitude_spectrum(samples, size);
+    let resolution = 2.0 * PI / (size as f64 * delta);
+    // Ignore anything weaker than the first sidelobe of the Hann window.
+    peaks(&spectrum, count, 0.05)
+        .into_iter()
+        .filter(|&k| k > 0 && k + 1 < spectrum.len())
+        .map(|k| {
+            let (a, b, c) = (
+                spectrum[k - 1].max(f64::MIN_POSITIVE).ln(),
+                spectrum[k].max(f64::MIN_POSITIVE).ln(),
+                spectrum[k + 1].max(f64::MIN_POSITIVE).ln(),
+            );
+            let denominator = a - 2.0 * b + c;
+            let offset = if denominator == 0.0 {
+                0.0
+            } else {
+                0.5 * (a - c) / denominator
+            };
+            Peak {
+                frequency: (k as f64 + offset) * resolution,
+                // A Hann window has a coherent gain of one half.
+                amplitude: 4.0 * (b - 0.25 * (a - c) * offset).exp() / samples.len() as f64,
+            }
+        })
+        .collect()
+}
+
+/// Short-time spectrum of `samples`: one column of bin magnitudes per window
+/// of `window` samples, with windows overlapping by three quarters. Long
+/// series use a larger hop so there are at most `max_columns` columns.
+/// Returns the hop, in samples, and the columns.
+pub fn spectrogram(samples: &[f64], window: usize, max_columns: usize) -> (usize, Vec<Vec<f64>>) {
+    let hop = (window / 4).max(1);
+    if samples.len() < window {
+        return (hop, Vec::new());
+    }
+    let span = samples.len() - window;
+    let hop = hop.max((span + max_columns.max(2) - 2) / (max_columns.max(2) - 1));
+    let columns = (0..=span / hop)
+        .map(|i| magnitude_spectrum(&samples[i * hop..i * hop + window], window))
+        .collect();
+    (hop, columns)
+}
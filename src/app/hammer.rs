@@ -0,0 +1,177 @@
+use super::string_dynamics::{check_finite, check_positive, nearest_mass, square};
+use std::collections::VecDeque;
+
+/// Number of contact force samples kept for the force plot.
+const HISTORY_LEN: usize = 10_000;
+
+/// A felt hammer that strikes the string from below.
+///
+/// The felt is modelled as a nonlinear spring that only pushes,
+/// `force = felt_stiffness * compression^felt_exponent`, so the hammer
+/// bounces off the string on its own once the compression vanishes.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hammer {
+    pub position: f64,
+    pub mass: f64,
+    pub felt_stiffness: f64,
+    pub felt_exponent: f64,
+    pub strike_velocity: f64,
+    pub start_distance: f64,
+    pos: f64,
+    vel: f64,
+    accel: f64,
+    in_flight: bool,
+    in_contact: bool,
+    #[serde(skip)]
+    force_history: VecDeque<[f64; 2]>,
+}
+
+impl Default for Hammer {
+    fn default() -> Self {
+        Self {
+            position: 0.125,
+            mass: 1.0,
+            felt_stiffness: 10.0,
+            felt_exponent: 2.5,
+            strike_velocity: 1.0,
+            start_distance: 0.5,
+            pos: 0.0,
+            vel: 0.0,
+            accel: 0.0,
+            in_flight: false,
+            in_contact: false,
+            force_history: VecDeque::new(),
+        }
+    }
+}
+
+impl Hammer {
+    /// Index of the struck mass for a string of `n` masses.
+    pub fn target(&self, n: usize) -> usize {
+        nearest_mass(self.position, n)
+    }
+
+    /// Launches the hammer towards a string whose struck mass sits at `string_pos`.
+    pub fn strike(&mut self, string_pos: f64) {
+        self.pos = string_pos - self.start_distance;
+        self.vel = self.strike_velocity;
+        self.accel = 0.0;
+        self.in_flight = true;
+        self.in_contact = false;
+        self.force_history.clear();
+    }
+
+    /// Stops the hammer and forgets its contact history.
+    pub fn stop(&mut self) {
+        self.in_flight = false;
+        self.in_contact = false;
+        self.force_history.clear();
+    }
+
+    pub fn in_flight(&self) -> bool {
+        self.in_flight
+    }
+
+    pub fn in_contact(&self) -> bool {
+        self.in_contact
+    }
+
+    pub fn get_pos(&self) -> f64 {
+        self.pos
+    }
+
+    pub fn force_history(&self) -> &VecDeque<[f64; 2]> {
+        &self.force_history
+    }
+
+    /// Checks that the hammer has a positive mass and felt stiffness and that
+    /// its settings and state are finite.
+    pub fn validate(&self) -> Result<(), String> {
+        check_positive("the hammer mass", self.mass)?;
+        check_positive("the felt stiffness", self.felt_stiffness)?;
+        for (what, value) in [
+            ("the strike position", self.position),
+            ("the felt exponent", self.felt_exponent),
+            ("the strike velocity", self.strike_velocity),
+            ("the start distance", self.start_distance),
+            ("the hammer position", self.pos),
+            ("the hammer velocity", self.vel),
+            ("the hammer acceleration", self.accel),
+        ] {
+            check_finite(what, value)?;
+        }
+        Ok(())
+    }
+
+    /// Moves the hammer to the end of the step, before the contact force is known.
+    pub fn update_position(&mut self, delta: f64) {
+        if self.in_flight {
+            self.pos += self.vel * delta + 0.5 * self.accel * square(delta);
+        }
+    }
+
+    /// Computes the felt force against a string at `string_pos` and finishes the
+    /// velocity update. Returns the force pushing the string upwards.
+    pub fn contact(&mut self, string_pos: f64, time: f64, delta: f64) -> f64 {
+        if !self.in_flight {
+            return 0.0;
+        }
+        let compression = self.pos - string_pos;
+        let force = if compression > 0.0 {
+            self.felt_stiffness * compression.powf(self.felt_exponent)
+        } else {
+            0.0
+        };
+        let accel = -force / self.mass;
+        self.vel += 0.5 * (self.accel + accel) * delta;
+        self.accel = accel;
+        while self.force_history.len() >= HISTORY_LEN {
+            self.force_history.pop_front();
+        }
+        self.force_history.push_back([time, force]);
+
+        self.in_contact = force > 0.0;
+        if !self.in_contact && self.vel <= 0.0 {
+            // Either the hammer has bounced off and is moving away for good, or
+            // it was launched too slowly to ever reach the string.
+            self.in_flight = false;
+        }
+        force
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn force_history_is_bounded() {
+        let mut hammer = Hammer {
+            start_distance: 1e9,
+            ..Default::default()
+        };
+        hammer.strike(0.0);
+        let delta = 0.01;
+        for i in 0..2 * HISTORY_LEN {
+            hammer.update_position(delta);
+            hammer.contact(0.0, i as f64 * delta, delta);
+        }
+        assert!(hammer.in_flight());
+        assert_eq!(hammer.force_history().len(), HISTORY_LEN);
+        assert_eq!(hammer.force_history()[0][0], HISTORY_LEN as f64 * delta);
+    }
+
+    #[test]
+    fn flight_ends_when_the_string_is_out_of_reach() {
+        let mut hammer = Hammer {
+            strike_velocity: 0.0,
+            ..Default::default()
+        };
+        hammer.strike(0.0);
+        hammer.update_position(0.01);
+        assert_eq!(hammer.contact(0.0, 0.0, 0.01), 0.0);
+        assert!(!hammer.in_flight());
+    }
+}
@@ -0,0 +1,74 @@
+use super::figure::{PlotExport, PlotFormat};
+use super::scenario::Scenario;
+
+pub const USAGE: &str = "\
+Usage: loaded_string_headless SCENARIO [OPTIONS]
+
+Loads a scenario file, runs it without a window and writes the results.
+
+Options:
+    --until TIME    run until TIME instead of the scenario's max time
+    --save PATH     save the final state as a scenario file
+    --plot PATH     export the final frame as an SVG or PNG image, chosen by
+                    the extension of PATH";
+
+/// Runs the command line `args`, without the program name, and returns the
+/// lines to print: one for every file written and one with the final time.
+pub fn run(args: &[String]) -> Result<Vec<String>, String> {
+    let mut scenario_path = None;
+    let mut until = None;
+    let mut save = None;
+    let mut plot = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--until" => {
+                let text = value()?;
+                until = Some(
+                    text.parse::<f64>()
+                        .map_err(|_| format!("{} is not a time", text))?,
+                );
+            }
+            "--save" => save = Some(value()?),
+            "--plot" => plot = Some(value()?),
+            "-h" | "--help" => return Ok(vec![USAGE.to_string()]),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ if scenario_path.is_none() => scenario_path = Some(arg.clone()),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    let scenario_path = scenario_path.ok_or("no scenario file given")?;
+    let text = std::fs::read_to_string(&scenario_path)
+        .map_err(|e| format!("could not read {}: {}", scenario_path, e))?;
+    let mut scenario = Scenario::from_ron(&text)
+        .map_err(|e| format!("could not load {}: {}", scenario_path, e))?;
+    let until = until
+        .or_else(|| scenario.stop_time())
+        .ok_or("the scenario is not clamped, so give the time to run until with --until")?;
+    scenario.run(until);
+
+    let mut written = Vec::new();
+    let mut write = |path: &str, contents: &[u8]| {
+        std::fs::write(path, contents).map_err(|e| format!("could not write {}: {}", path, e))?;
+        written.push(format!("wrote {}", path));
+        Ok::<(), String>(())
+    };
+    if let Some(path) = &save {
+        write(path, scenario.to_ron()?.as_bytes())?;
+    }
+    if let Some(path) = &plot {
+        let format = if path.to_lowercase().ends_with(".png") {
+            PlotFormat::Png
+        } else {
+            PlotFormat::Svg
+        };
+        let export = PlotExport {
+            format,
+            ..Default::default()
+        };
+        write(path, &export.render(&scenario.system, &scenario.display)?)?;
+    }
+    written.push(format!("stopped at time {:.3}", scenario.time));
+    Ok(written)
+}
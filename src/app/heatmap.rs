@@ -0,0 +1,167 @@
+use egui::{Color32, ColorImage, TextureHandle};
+
+// Control points of the viridis colour map.
+const VIRIDIS: [[f64; 3]; 5] = [
+    [68.0, 1.0, 84.0],
+    [59.0, 82.0, 139.0],
+    [33.0, 145.0, 140.0],
+    [94.0, 201.0, 98.0],
+    [253.0, 231.0, 37.0],
+];
+const GRAYSCALE: [[f64; 3]; 2] = [[0.0, 0.0, 0.0], [255.0, 255.0, 255.0]];
+const DIVERGING: [[f64; 3]; 3] = [
+    [40.0, 80.0, 200.0],
+    [245.0, 245.0, 245.0],
+    [200.0, 40.0, 40.0],
+];
+
+/// Colour map used to draw heat maps.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Colormap {
+    Viridis,
+    Grayscale,
+    /// Blue for negative values, white for zero, red for positive values.
+    Diverging,
+}
+
+impl std::fmt::Display for Colormap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Colormap::Viridis => write!(f, "Viridis"),
+            Colormap::Grayscale => write!(f, "Grayscale"),
+            Colormap::Diverging => write!(f, "Diverging"),
+        }
+    }
+}
+
+impl Colormap {
+    /// Maps `t` in [0, 1] to a colour.
+    pub fn color(&self, t: f64) -> Color32 {
+        let stops: &[[f64; 3]] = match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Grayscale => &GRAYSCALE,
+            Colormap::Diverging => &DIVERGING,
+        };
+        let s = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+        let i = (s.floor() as usize).min(stops.len() - 2);
+        let f = s - i as f64;
+        let channel = |c: usize| (stops[i][c] + (stops[i + 1][c] - stops[i][c]) * f) as u8;
+        Color32::from_rgb(channel(0), channel(1), channel(2))
+    }
+}
+
+/// Builds an image with one column per entry of `columns`, the first entry of
+/// each column at the bottom. Values are normalized to their overall range, or
+/// symmetrically around zero for the diverging colour map.
+///
+/// Textures larger than the GPU allows make the backend panic, so blocks of
+/// neighbouring values are averaged into one pixel when either side would be
+/// larger than `max_side`.
+pub fn heat_image(columns: &[Vec<f64>], colormap: Colormap, max_side: usize) -> ColorImage {
+    let max_side = max_side.max(1);
+    let shrunk;
+    let columns = if columns.len() > max_side || columns.iter().any(|c| c.len() > max_side) {
+        shrunk = shrink(columns, max_side);
+        &shrunk
+    } else {
+        columns
+    };
+    let width = columns.len().max(1);
+    let height = columns.iter().map(|c| c.len()).max().unwrap_or(1).max(1);
+    let (low, high) = columns
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    let (low, high) = if colormap == Colormap::Diverging {
+        let largest = low.abs().max(high.abs());
+        (-largest, largest)
+    } else {
+        (low, high)
+    };
+    let range = if high > low { high - low } else { 1.0 };
+    let mut image = ColorImage::new([width, height], Color32::BLACK);
+    for (x, column) in columns.iter().enumerate() {
+        for (y, &value) in column.iter().enumerate() {
+            image.pixels[(height - 1 - y) * width + x] = colormap.color((value - low) / range);
+        }
+    }
+    image
+}
+
+/// Averages `columns` over blocks of neighbouring columns and rows, so there
+/// are at most `max_side` of each.
+fn shrink(columns: &[Vec<f64>], max_side: usize) -> Vec<Vec<f64>> {
+    let height = columns.iter().map(|c| c.len()).max().unwrap_or(0);
+    let (width, rows) = (columns.len().min(max_side), height.min(max_side));
+    (0..width)
+        .map(|x| {
+            let group = &columns[x * columns.len() / width..(x + 1) * columns.len() / width];
+            (0..rows)
+                .map_while(|y| {
+                    let (a, b) = (y * height / rows, (y + 1) * height / rows);
+                    let (sum, count) = group
+                        .iter()
+                        .flat_map(|c| c.get(a.min(c.len())..b.min(c.len())).unwrap_or(&[]))
+                        .fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+                    (count > 0).then(|| sum / count as f64)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A texture that is created on first use and updated in place afterwards.
+#[derive(Clone, Default)]
+pub struct HeatmapTexture {
+    handle: Option<TextureHandle>,
+}
+
+impl std::fmt::Debug for HeatmapTexture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HeatmapTexture").finish()
+    }
+}
+
+impl HeatmapTexture {
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        name: &str,
+        image: ColorImage,
+    ) -> egui::TextureId {
+        match &mut self.handle {
+            Some(handle) => handle.set(image),
+            None => self.handle = Some(ctx.load_texture(name, image)),
+        }
+        self.id()
+    }
+
+    /// Id of the current texture, without changing it.
+    pub fn id(&self) -> egui::TextureId {
+        self.handle
+            .as_ref()
+            .map_or_else(Default::default, |h| h.id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_images_are_shrunk() {
+        let columns: Vec<Vec<f64>> = (0..5)
+            .map(|x| (0..10).map(|y| (x * y) as f64).collect())
+            .collect();
+        assert_eq!(heat_image(&columns, Colormap::Viridis, 16).size, [5, 10]);
+        assert_eq!(heat_image(&columns, Colormap::Viridis, 4).size, [4, 4]);
+        let shrunk = shrink(&columns, 4);
+        // The last block averages columns 3 and 4 over rows 7 to 9.
+        assert_eq!(
+            shrunk[3][3],
+            (21.0 + 24.0 + 27.0 + 28.0 + 32.0 + 36.0) / 6.0
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use super::string_dynamics::Sys;
+use std::collections::VecDeque;
+
+/// A state of the string at a step of the timeline.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    step: usize,
+    time: f64,
+    system: Sys,
+}
+
+/// Bounded, rewindable history of the simulation.
+///
+/// A full snapshot is kept every `interval` steps, and any state in between is
+/// rebuilt by replaying the integrator from the keyframe before it. Edits
+/// made between two steps start a new keyframe, so replays stay exact. Only
+/// the latest `capacity` keyframes are kept.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone)]
+pub struct History {
+    pub interval: usize,
+    pub capacity: usize,
+    #[serde(skip)]
+    keyframes: VecDeque<Snapshot>,
+    /// Step of the latest recorded state.
+    #[serde(skip)]
+    end: usize,
+    /// The state the app is showing, as far as the history knows.
+    #[serde(skip)]
+    current: Option<Snapshot>,
+    #[serde(skip)]
+    delta: f64,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            interval: 50,
+            capacity: 200,
+            keyframes: VecDeque::new(),
+            end: 0,
+            current: None,
+            delta: 0.0,
+        }
+    }
+}
+
+impl History {
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+        self.end = 0;
+        self.current = None;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// First and last step that can be shown.
+    pub fn range(&self) -> (usize, usize) {
+        (self.keyframes.front().map_or(0, |k| k.step), self.end)
+    }
+
+    /// Step of the state being shown, if it is part of the history.
+    pub fn cursor(&self) -> Option<usize> {
+        self.current.as_ref().map(|c| c.step)
+    }
+
+    /// Time of the state at `step`, assuming no edits since the keyframe.
+    pub fn time_at(&self, step: usize) -> f64 {
+        self.keyframe_before(step)
+            .map_or(0.0, |k| k.time + (step - k.step) as f64 * self.delta)
+    }
+
+    fn keyframe_before(&self, step: usize) -> Option<&Snapshot> {
+        self.keyframes.iter().rev().find(|k| k.step <= step)
+    }
+
+    /// Call before advancing `system` by one step of `delta` from `time`.
+    ///
+    /// Continuing from an earlier point of the timeline drops the states after
+    /// it. A change of time or time-step starts a new history, and any other
+    /// change since the last recorded state becomes a keyframe.
+    pub fn before_step(&mut self, system: &Sys, time: f64, delta: f64) {
+        let current = match &self.current {
+            Some(current) if delta == self.delta && time == current.time => current,
+            _ => {
+                self.clear();
+                self.delta = delta;
+                self.push_keyframe(0, time, system);
+                return;
+            }
+        };
+        let step = current.step;
+        let edited = current.system != *system;
+        self.keyframes.retain(|k| k.step <= step);
+        self.end = step;
+        let last = self.keyframes.back().map_or(0, |k| k.step);
+        if edited || step >= last + self.interval.max(1) {
+            if last == step {
+                self.keyframes.pop_back();
+            }
+            self.push_keyframe(step, time, system);
+        }
+    }
+
+    /// Call after each step with the new state.
+    pub fn after_step(&mut self, system: &Sys, time: f64) {
+        if self.keyframes.is_empty() {
+            return;
+        }
+        self.end += 1;
+        self.current = Some(Snapshot {
+            step: self.end,
+            time,
+            system: system.clone(),
+        });
+    }
+
+    /// Call after stepping `system` back by one step to `time`. The new state
+    /// replaces the history after it, as stepping backward is not exact when
+    /// there are dissipative forces.
+    pub fn after_step_back(&mut self, system: &Sys, time: f64) {
+        match self.cursor() {
+            Some(step) if step > self.range().0 => {
+                let step = step - 1;
+                self.keyframes.retain(|k| k.step < step);
+                self.push_keyframe(step, time, system);
+                self.current = Some(Snapshot {
+                    step,
+                    time,
+                    system: system.clone(),
+                });
+            }
+            _ => self.clear(),
+        }
+    }
+
+    fn push_keyframe(&mut self, step: usize, time: f64, system: &Sys) {
+        while self.keyframes.len() >= self.capacity.max(1) {
+            self.keyframes.pop_front();
+        }
+        self.keyframes.push_back(Snapshot {
+            step,
+            time,
+            system: system.clone(),
+        });
+        self.end = step;
+    }
+
+    /// Rebuilds the state at `step`, clamped to the recorded range, and
+    /// makes it the current state. Returns the system and its time.
+    pub fn seek(&mut self, step: usize) -> Option<(Sys, f64)> {
+        let (start, end) = self.range();
+        let step = step.clamp(start, end);
+        let keyframe = self.keyframe_before(step)?;
+        // Replaying forward from the shown state is cheaper when possible.
+        let from = match &self.current {
+            Some(current) if current.step <= step && current.step >= keyframe.step => current,
+            _ => keyframe,
+        };
+        let mut system = from.system.clone();
+        let mut time = from.time;
+        for _ in from.step..step {
+            system.update_system(&mut time, self.delta);
+        }
+        self.current = Some(Snapshot {
+            step,
+            time,
+            system: system.clone(),
+        });
+        Some((system, time))
+    }
+}
@@ -0,0 +1,66 @@
+use super::heatmap::Colormap;
+use super::string_dynamics::Sys;
+use std::collections::VecDeque;
+
+/// History of the whole string shape, one row per recorded time-step.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone)]
+pub struct Kymograph {
+    pub capacity: usize,
+    pub visible: usize,
+    pub scroll: usize,
+    pub colormap: Colormap,
+    #[serde(skip)]
+    rows: VecDeque<(f64, Vec<f64>)>,
+}
+
+impl Default for Kymograph {
+    fn default() -> Self {
+        Self {
+            capacity: 2000,
+            visible: 500,
+            scroll: 0,
+            colormap: Colormap::Diverging,
+            rows: VecDeque::new(),
+        }
+    }
+}
+
+impl Kymograph {
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+        self.scroll = 0;
+    }
+
+    /// Appends the current shape. The history restarts if the number of masses changed.
+    pub fn record(&mut self, system: &Sys, time: f64) {
+        if self
+            .rows
+            .back()
+            .map_or(false, |(_, row)| row.len() != system.len())
+        {
+            self.rows.clear();
+        }
+        while self.rows.len() >= self.capacity.max(1) {
+            self.rows.pop_front();
+        }
+        self.rows.push_back((time, system.positions()));
+    }
+
+    /// Every recorded shape, oldest first.
+    pub fn shapes(&self) -> Vec<&Vec<f64>> {
+        self.rows.iter().map(|(_, row)| row).collect()
+    }
+
+    /// The rows in view, `scroll` rows back from the latest one.
+    pub fn window(&self) -> Vec<&(f64, Vec<f64>)> {
+        let end = self.rows.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(self.visible);
+        self.rows.range(start..end).collect()
+    }
+}
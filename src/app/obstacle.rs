@@ -0,0 +1,105 @@
+use super::string_dynamics::{check_finite, square};
+
+/// Shape of a rigid profile lying under the string.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum ObstacleShape {
+    /// A single point support, like a fret.
+    Fret,
+    /// A parabolic bump of the given width, like a sitar bridge.
+    Bridge,
+    /// A flat floor of the given width.
+    Wall,
+}
+
+impl std::fmt::Display for ObstacleShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObstacleShape::Fret => write!(f, "Fret"),
+            ObstacleShape::Bridge => write!(f, "Bridge"),
+            ObstacleShape::Wall => write!(f, "Wall"),
+        }
+    }
+}
+
+/// How masses are kept from passing through obstacles.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum ContactModel {
+    /// A stiff spring pushes masses back out of the obstacle.
+    Penalty,
+    /// Masses are projected onto the profile and lose their velocity.
+    Exact,
+}
+
+impl std::fmt::Display for ContactModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContactModel::Penalty => write!(f, "Penalty"),
+            ContactModel::Exact => write!(f, "Exact"),
+        }
+    }
+}
+
+/// An obstacle under the string. `position` and `width` are fractions of the
+/// string length, `height` and `curvature` are in displacement units.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Obstacle {
+    pub shape: ObstacleShape,
+    pub position: f64,
+    pub height: f64,
+    pub width: f64,
+    pub curvature: f64,
+}
+
+impl Default for Obstacle {
+    fn default() -> Self {
+        Self {
+            shape: ObstacleShape::Fret,
+            position: 0.5,
+            height: -0.5,
+            width: 0.2,
+            curvature: 10.0,
+        }
+    }
+}
+
+impl Obstacle {
+    /// Height of the profile at `x` (a fraction of the string length), if the
+    /// obstacle reaches that far. `spacing` is the distance between masses,
+    /// used to give a fret the width of a single mass.
+    pub fn profile(&self, x: f64, spacing: f64) -> Option<f64> {
+        let offset = x - self.position;
+        match self.shape {
+            ObstacleShape::Fret => (offset.abs() <= 0.5 * spacing).then(|| self.height),
+            ObstacleShape::Bridge => (offset.abs() <= 0.5 * self.width)
+                .then(|| self.height - self.curvature * square(offset)),
+            ObstacleShape::Wall => (offset.abs() <= 0.5 * self.width).then(|| self.height),
+        }
+    }
+
+    /// Checks that the position and shape of the obstacle are finite.
+    pub fn validate(&self) -> Result<(), String> {
+        check_finite("the position", self.position)?;
+        check_finite("the height", self.height)?;
+        check_finite("the width", self.width)?;
+        check_finite("the curvature", self.curvature)
+    }
+
+    /// Points outlining the profile, as (fraction of length, height) pairs.
+    pub fn outline(&self) -> Vec<[f64; 2]> {
+        match self.shape {
+            ObstacleShape::Fret => vec![[self.position, self.height]],
+            ObstacleShape::Bridge => (0..=32)
+                .map(|i| {
+                    let x = self.position + self.width * (i as f64 / 32.0 - 0.5);
+                    [x, self.height - self.curvature * square(x - self.position)]
+                })
+                .collect(),
+            ObstacleShape::Wall => vec![
+                [self.position - 0.5 * self.width, self.height],
+                [self.position + 0.5 * self.width, self.height],
+            ],
+        }
+    }
+}
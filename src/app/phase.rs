@@ -0,0 +1,151 @@
+use super::string_dynamics::Sys;
+use super::transform::sine_coefficient;
+use std::collections::VecDeque;
+
+/// Number of trail points kept for the phase portrait.
+const TRAIL_LEN: usize = 5000;
+/// Number of points kept for the Poincaré section.
+const SECTION_LEN: usize = 20_000;
+
+/// The pair of coordinates drawn against each other.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum PhaseKind {
+    /// Displacement of one mass against its velocity.
+    Mass,
+    /// Amplitude of one mode against the amplitude of another.
+    Modes,
+}
+
+impl std::fmt::Display for PhaseKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PhaseKind::Mass => write!(f, "Mass displacement / velocity"),
+            PhaseKind::Modes => write!(f, "Mode amplitude / mode amplitude"),
+        }
+    }
+}
+
+/// Coordinate whose upward zero crossings trigger the Poincaré section.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum SectionSource {
+    MassDisplacement,
+    ModeAmplitude,
+}
+
+impl std::fmt::Display for SectionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SectionSource::MassDisplacement => write!(f, "Mass displacement"),
+            SectionSource::ModeAmplitude => write!(f, "Mode amplitude"),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone)]
+pub struct PhasePortrait {
+    pub kind: PhaseKind,
+    pub mass: usize,
+    pub mode_x: usize,
+    pub mode_y: usize,
+    pub poincare: bool,
+    pub section_source: SectionSource,
+    pub section_index: usize,
+    #[serde(skip)]
+    trail: VecDeque<[f64; 2]>,
+    #[serde(skip)]
+    section: VecDeque<[f64; 2]>,
+    #[serde(skip)]
+    last: Option<([f64; 2], f64)>,
+}
+
+impl Default for PhasePortrait {
+    fn default() -> Self {
+        Self {
+            kind: PhaseKind::Mass,
+            mass: 0,
+            mode_x: 1,
+            mode_y: 2,
+            poincare: false,
+            section_source: SectionSource::ModeAmplitude,
+            section_index: 1,
+            trail: VecDeque::new(),
+            section: VecDeque::new(),
+            last: None,
+        }
+    }
+}
+
+impl PhasePortrait {
+    pub fn trail(&self) -> &VecDeque<[f64; 2]> {
+        &self.trail
+    }
+
+    pub fn section(&self) -> &VecDeque<[f64; 2]> {
+        &self.section
+    }
+
+    pub fn clear(&mut self) {
+        self.trail.clear();
+        self.section.clear();
+        self.last = None;
+    }
+
+    /// Axis labels of the portrait.
+    pub fn labels(&self) -> (String, String) {
+        match self.kind {
+            PhaseKind::Mass => (
+                format!("displacement of mass {}", self.mass),
+                format!("velocity of mass {}", self.mass),
+            ),
+            PhaseKind::Modes => (
+                format!("amplitude of mode {}", self.mode_x),
+                format!("amplitude of mode {}", self.mode_y),
+            ),
+        }
+    }
+
+    /// Adds the current state to the trail and, when the section coordinate
+    /// crosses zero upwards, the interpolated crossing to the Poincaré section.
+    pub fn record(&mut self, system: &Sys, delta: f64) {
+        let n = system.len();
+        let positions = system.positions();
+        let point = match self.kind {
+            PhaseKind::Mass => {
+                let i = self.mass.min(n - 1);
+                [system.get_mass_pos(i), system.get_mass_vel(i, delta)]
+            }
+            PhaseKind::Modes => [
+                sine_coefficient(&positions, self.mode_x),
+                sine_coefficient(&positions, self.mode_y),
+            ],
+        };
+        let coordinate = match self.section_source {
+            SectionSource::MassDisplacement => positions[self.section_index.min(n - 1)],
+            SectionSource::ModeAmplitude => sine_coefficient(&positions, self.section_index),
+        };
+        if let (true, Some((previous, last))) = (self.poincare, self.last) {
+            if last < 0.0 && coordinate >= 0.0 {
+                let t = -last / (coordinate - last);
+                push_bounded(
+                    &mut self.section,
+                    [
+                        previous[0] + t * (point[0] - previous[0]),
+                        previous[1] + t * (point[1] - previous[1]),
+                    ],
+                    SECTION_LEN,
+                );
+            }
+        }
+        self.last = Some((point, coordinate));
+        push_bounded(&mut self.trail, point, TRAIL_LEN);
+    }
+}
+
+fn push_bounded(buffer: &mut VecDeque<[f64; 2]>, point: [f64; 2], capacity: usize) {
+    if buffer.len() == capacity {
+        buffer.pop_front();
+    }
+    buffer.push_back(point);
+}
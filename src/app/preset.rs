@@ -0,0 +1,94 @@
+use super::scenario::Scenario;
+use super::string_dynamics::Sys;
+
+/// A scenario saved under a name.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub scenario: Scenario,
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Self {
+            name: "Preset".to_string(),
+            scenario: Default::default(),
+        }
+    }
+}
+
+impl Preset {
+    fn new(name: &str, description: &str, system: Sys) -> Preset {
+        Preset {
+            name: name.to_string(),
+            scenario: Scenario {
+                description: description.to_string(),
+                system,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Presets that ship with the app, rebuilt on every start.
+pub fn built_in() -> Vec<Preset> {
+    let mut fundamental = Sys::new(0, 20, 0.0);
+    fundamental.harmonic_state(1.0, 1);
+
+    let mut pluck = Sys::new(0, 20, 0.0);
+    pluck.pluck(1.0, 0.25);
+
+    let mut fput = Sys::new(0, 32, 0.0);
+    fput.nonlinearity = 8.0;
+    fput.harmonic_state(1.0, 1);
+    let mut fput = Preset::new(
+        "FPUT",
+        "Fundamental mode of a chain of 32 masses with cubic spring forces. The energy \
+         spreads to higher modes and returns almost entirely to the fundamental around \
+         time 5000.",
+        fput,
+    );
+    fput.scenario.delta = 0.25;
+    fput.scenario.display.show_spectrum = true;
+
+    let mut sag = Sys::new(0, 20, 0.0);
+    sag.gravity = 0.02;
+    for i in 0..sag.len() {
+        sag.set_finger(i, 0.1);
+    }
+
+    let mut bowed = Sys::new(0, 30, 0.0);
+    bowed.bow.enabled = true;
+    bowed.set_finger(bowed.len() - 1, 0.3);
+    let mut bowed = Preset::new(
+        "Bowed string",
+        "30 masses bowed an eighth of the way along, with a light damper on the last \
+         mass. The bowed mass settles into Helmholtz motion: it sticks to the bow for \
+         most of each period and slips back once.",
+        bowed,
+    );
+    bowed.scenario.delta = 0.05;
+
+    vec![
+        Preset::new(
+            "Fundamental mode",
+            "The lowest normal mode of 20 masses, which oscillates without changing shape.",
+            fundamental,
+        ),
+        Preset::new(
+            "Pluck",
+            "20 masses plucked a quarter of the way along, released from rest.",
+            pluck,
+        ),
+        fput,
+        Preset::new(
+            "Gravity sag",
+            "A flat string of 20 masses released under gravity. Light damping lets it \
+             settle into a parabolic sag.",
+            sag,
+        ),
+        bowed,
+    ]
+}
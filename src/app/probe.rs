@@ -0,0 +1,183 @@
+use super::string_dynamics::Sys;
+use egui::Color32;
+use std::collections::VecDeque;
+
+const PALETTE: [Color32; 6] = [
+    Color32::from_rgb(230, 160, 60),
+    Color32::from_rgb(90, 170, 230),
+    Color32::from_rgb(220, 90, 200),
+    Color32::from_rgb(230, 230, 80),
+    Color32::from_rgb(90, 220, 200),
+    Color32::from_rgb(240, 120, 120),
+];
+
+/// Which recorded quantity is plotted.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Quantity {
+    Displacement,
+    Velocity,
+    Acceleration,
+}
+
+impl std::fmt::Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Quantity::Displacement => write!(f, "Displacement"),
+            Quantity::Velocity => write!(f, "Velocity"),
+            Quantity::Acceleration => write!(f, "Acceleration"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub time: f64,
+    pub pos: f64,
+    pub vel: f64,
+    pub accel: f64,
+}
+
+impl Sample {
+    pub fn get(&self, quantity: Quantity) -> f64 {
+        match quantity {
+            Quantity::Displacement => self.pos,
+            Quantity::Velocity => self.vel,
+            Quantity::Acceleration => self.accel,
+        }
+    }
+}
+
+/// Records the motion of one mass.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone)]
+pub struct Probe {
+    pub mass: usize,
+    pub color: Color32,
+    #[serde(skip)]
+    samples: VecDeque<Sample>,
+}
+
+impl Default for Probe {
+    fn default() -> Self {
+        Self {
+            mass: 0,
+            color: PALETTE[0],
+            samples: VecDeque::new(),
+        }
+    }
+}
+
+impl Probe {
+    pub fn samples(&self) -> &VecDeque<Sample> {
+        &self.samples
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+/// A set of probes sharing one ring buffer length. All samples are taken
+/// at the same time-step, `delta`, which the spectral analysis relies on.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone)]
+pub struct Probes {
+    pub probes: Vec<Probe>,
+    pub capacity: usize,
+    pub quantity: Quantity,
+    #[serde(skip)]
+    delta: f64,
+}
+
+impl Default for Probes {
+    fn default() -> Self {
+        Self {
+            probes: Vec::new(),
+            capacity: 2000,
+            quantity: Quantity::Displacement,
+            delta: 0.0,
+        }
+    }
+}
+
+impl Probes {
+    pub fn add(&mut self, mass: usize) {
+        self.probes.push(Probe {
+            mass,
+            color: PALETTE[self.probes.len() % PALETTE.len()],
+            samples: VecDeque::new(),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        for probe in self.probes.iter_mut() {
+            probe.clear();
+        }
+    }
+
+    /// Appends the current state of every probed mass, dropping the oldest
+    /// samples once the buffer is full. Probes past the end of the string are skipped.
+    /// A new time-step, or a time no later than the last sample, as after a
+    /// reset, starts new recordings.
+    pub fn record(&mut self, system: &Sys, time: f64, delta: f64) {
+        if delta != self.delta {
+            self.clear();
+            self.delta = delta;
+        }
+        for probe in self.probes.iter_mut() {
+            if probe.mass >= system.len() {
+                continue;
+            }
+            if probe.samples.back().map_or(false, |sample| time <= sample.time) {
+                probe.clear();
+            }
+            while probe.samples.len() >= self.capacity.max(1) {
+                probe.samples.pop_front();
+            }
+            probe.samples.push_back(Sample {
+                time,
+                pos: system.get_mass_pos(probe.mass),
+                vel: system.get_mass_vel(probe.mass, delta),
+                accel: system.get_mass_accel(probe.mass),
+            });
+        }
+    }
+
+    /// All recorded samples as CSV, one row per probe and sample.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("probe,mass,time,displacement,velocity,acceleration\n");
+        for (i, probe) in self.probes.iter().enumerate() {
+            for sample in &probe.samples {
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{}\n",
+                    i, probe.mass, sample.time, sample.pos, sample.vel, sample.accel
+                ));
+            }
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recordings_restart() {
+        let system = Sys::new(0, 10, 1.0);
+        let mut probes = Probes::default();
+        probes.add(0);
+        for i in 1..=5 {
+            probes.record(&system, i as f64 * 0.1, 0.1);
+        }
+        assert_eq!(probes.probes[0].samples().len(), 5);
+        // Back in time, as after a reset.
+        probes.record(&system, 0.1, 0.1);
+        assert_eq!(probes.probes[0].samples().len(), 1);
+        probes.record(&system, 0.3, 0.1);
+        probes.record(&system, 0.35, 0.05);
+        assert_eq!(probes.probes[0].samples().len(), 1);
+    }
+}
@@ -0,0 +1,222 @@
+use egui::Color32;
+use image::{Rgba, RgbaImage};
+
+/// Rows of the 5x7 bitmap glyphs used for axis labels, top row first and the
+/// leftmost column in the highest of the five bits.
+fn glyph(c: char) -> [u8; 7] {
+    match c {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        'e' => [0x00, 0x00, 0x0E, 0x11, 0x1F, 0x10, 0x0E],
+        _ => [0; 7],
+    }
+}
+
+/// Horizontal alignment of text relative to its position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Centre,
+}
+
+/// A small anti-aliased CPU rasterizer, so images can be drawn without a GPU.
+/// Coordinates are in pixels with y pointing down.
+pub struct Canvas {
+    image: RgbaImage,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, background: Color32) -> Self {
+        let [r, g, b, a] = background.to_array();
+        Self {
+            image: RgbaImage::from_pixel(width.max(1), height.max(1), Rgba([r, g, b, a])),
+        }
+    }
+
+    /// Blends `color` into the pixel at (x, y) with the given coverage.
+    fn blend(&mut self, x: i64, y: i64, color: Color32, coverage: f64) {
+        let (width, height) = self.image.dimensions();
+        if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+            return;
+        }
+        let alpha = coverage.clamp(0.0, 1.0) * color.a() as f64 / 255.0;
+        if alpha <= 0.0 {
+            return;
+        }
+        // Colours are premultiplied, so undo that before blending.
+        let unmultiplied = |c: u8| (c as f64 * 255.0 / color.a() as f64).min(255.0);
+        let source = [
+            unmultiplied(color.r()),
+            unmultiplied(color.g()),
+            unmultiplied(color.b()),
+        ];
+        let pixel = self.image.get_pixel_mut(x as u32, y as u32);
+        for (channel, source) in pixel.0.iter_mut().zip(source) {
+            *channel = (*channel as f64 * (1.0 - alpha) + source * alpha).round() as u8;
+        }
+    }
+
+    /// Blends `color` over the pixels within `reach` of the rectangle spanned
+    /// by `low` and `high`, with coverage given by `coverage` at pixel centres.
+    fn fill(
+        &mut self,
+        low: [f64; 2],
+        high: [f64; 2],
+        reach: f64,
+        color: Color32,
+        coverage: impl Fn([f64; 2]) -> f64,
+    ) {
+        let (width, height) = self.image.dimensions();
+        let x0 = (low[0] - reach).floor().max(0.0) as i64;
+        let x1 = (high[0] + reach).ceil().min(width as f64) as i64;
+        let y0 = (low[1] - reach).floor().max(0.0) as i64;
+        let y1 = (high[1] + reach).ceil().min(height as f64) as i64;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let c = coverage([x as f64 + 0.5, y as f64 + 0.5]);
+                self.blend(x, y, color, c);
+            }
+        }
+    }
+
+    /// Draws a polyline with the given width.
+    pub fn polyline(&mut self, points: &[[f64; 2]], width: f64, color: Color32) {
+        let (columns, rows) = self.image.dimensions();
+        let half = 0.5 * width;
+        // Coverage of the whole line, taking the maximum over the segments so
+        // that the joints are not blended twice.
+        let mut mask = vec![0.0; (columns * rows) as usize];
+        for segment in points.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            if !(a.iter().chain(&b).all(|v| v.is_finite())) {
+                continue;
+            }
+            let reach = half + 1.0;
+            let x0 = (a[0].min(b[0]) - reach).floor().max(0.0) as usize;
+            let x1 = (a[0].max(b[0]) + reach).ceil().clamp(0.0, columns as f64) as usize;
+            let y0 = (a[1].min(b[1]) - reach).floor().max(0.0) as usize;
+            let y1 = (a[1].max(b[1]) + reach).ceil().clamp(0.0, rows as f64) as usize;
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let p = [x as f64 + 0.5, y as f64 + 0.5];
+                    let coverage = half + 0.5 - segment_distance(p, a, b);
+                    let cell = &mut mask[y * columns as usize + x];
+                    *cell = coverage.max(*cell);
+                }
+            }
+        }
+        for (i, &coverage) in mask.iter().enumerate() {
+            if coverage > 0.0 {
+                let (x, y) = (i % columns as usize, i / columns as usize);
+                self.blend(x as i64, y as i64, color, coverage);
+            }
+        }
+    }
+
+    /// Draws a filled circle.
+    pub fn circle(&mut self, centre: [f64; 2], radius: f64, color: Color32) {
+        let c = centre;
+        self.fill(c, c, radius + 1.0, color, |p| {
+            radius + 0.5 - ((p[0] - c[0]).powi(2) + (p[1] - c[1]).powi(2)).sqrt()
+        });
+    }
+
+    /// Fills a convex polygon given by its corners in either winding order.
+    pub fn polygon(&mut self, corners: &[[f64; 2]], color: Color32) {
+        if corners.len() < 3 {
+            return;
+        }
+        let low = corners
+            .iter()
+            .fold([f64::INFINITY; 2], |l, p| [l[0].min(p[0]), l[1].min(p[1])]);
+        let high = corners.iter().fold([f64::NEG_INFINITY; 2], |h, p| {
+            [h[0].max(p[0]), h[1].max(p[1])]
+        });
+        let centre = [0.5 * (low[0] + high[0]), 0.5 * (low[1] + high[1])];
+        self.fill(low, high, 1.0, color, |p| {
+            // Distance outside the nearest edge, measured along its normal.
+            let outside = (0..corners.len())
+                .map(|i| {
+                    let a = corners[i];
+                    let b = corners[(i + 1) % corners.len()];
+                    let normal = [b[1] - a[1], a[0] - b[0]];
+                    let length = (normal[0].powi(2) + normal[1].powi(2)).sqrt();
+                    let side = |q: [f64; 2]| {
+                        ((q[0] - a[0]) * normal[0] + (q[1] - a[1]) * normal[1]) / length
+                    };
+                    // Orient the normal away from the centre.
+                    if side(centre) > 0.0 {
+                        -side(p)
+                    } else {
+                        side(p)
+                    }
+                })
+                .fold(f64::NEG_INFINITY, f64::max);
+            0.5 - outside
+        });
+    }
+
+    /// Draws `text` with the bitmap font, `size` pixels high and vertically
+    /// centred on `position`. Only digits, signs, points and `e` are drawn.
+    pub fn text(
+        &mut self,
+        position: [f64; 2],
+        text: &str,
+        size: f64,
+        color: Color32,
+        align: Align,
+    ) {
+        let cell = (size / 7.0).round().max(1.0) as i64;
+        let advance = 6 * cell;
+        let width = advance * text.chars().count() as i64 - cell;
+        let left = position[0].round() as i64
+            - match align {
+                Align::Left => 0,
+                Align::Centre => width / 2,
+            };
+        let top = position[1].round() as i64 - 7 * cell / 2;
+        for (k, c) in text.chars().enumerate() {
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in 0..5 {
+                    if bits & (0x10 >> column) == 0 {
+                        continue;
+                    }
+                    let x = left + k as i64 * advance + column * cell;
+                    let y = top + row as i64 * cell;
+                    for dy in 0..cell {
+                        for dx in 0..cell {
+                            self.blend(x + dx, y + dy, color, 1.0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn into_image(self) -> RgbaImage {
+        self.image
+    }
+}
+
+/// Distance from `p` to the segment from `a` to `b`.
+fn segment_distance(p: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    let d = [b[0] - a[0], b[1] - a[1]];
+    let length = d[0] * d[0] + d[1] * d[1];
+    let t = if length > 0.0 {
+        (((p[0] - a[0]) * d[0] + (p[1] - a[1]) * d[1]) / length).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    ((p[0] - a[0] - t * d[0]).powi(2) + (p[1] - a[1] - t * d[1]).powi(2)).sqrt()
+}
@@ -0,0 +1,70 @@
+use super::string_dynamics::interpolate;
+use super::transform::{sine_series, sine_transform};
+
+/// How values on the masses are carried over when the number of masses changes.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Resampling {
+    Linear,
+    Cubic,
+    Spectral,
+}
+
+impl std::fmt::Display for Resampling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Resampling::Linear => write!(f, "Linear"),
+            Resampling::Cubic => write!(f, "Cubic"),
+            Resampling::Spectral => write!(f, "Spectral"),
+        }
+    }
+}
+
+/// Resamples `values`, taken on a string fixed at both ends, onto `size` masses.
+pub fn resample(values: &[f64], size: usize, method: Resampling) -> Vec<f64> {
+    let n = values.len();
+    // Values padded with the fixed boundaries.
+    let padded: Vec<f64> = std::iter::once(0.0)
+        .chain(values.iter().copied())
+        .chain(std::iter::once(0.0))
+        .collect();
+    let xs = (0..size).map(|i| (i as f64 + 1.0) / ((size + 1) as f64));
+    match method {
+        Resampling::Linear => {
+            let points: Vec<[f64; 2]> = padded
+                .iter()
+                .enumerate()
+                .map(|(i, &y)| [i as f64 / (n + 1) as f64, y])
+                .collect();
+            xs.map(|x| interpolate(&points, x)).collect()
+        }
+        Resampling::Cubic => {
+            // Catmull-Rom spline, with the string mirrored through the
+            // boundaries so the curvature vanishes there.
+            let at = |j: isize| -> f64 {
+                if j < 0 {
+                    -padded[(-j) as usize]
+                } else if j > (n + 1) as isize {
+                    -padded[2 * (n + 1) - j as usize]
+                } else {
+                    padded[j as usize]
+                }
+            };
+            xs.map(|x| {
+                let s = x * (n + 1) as f64;
+                let j = (s.floor() as isize).min(n as isize);
+                let t = s - j as f64;
+                let (p0, p1, p2, p3) = (at(j - 1), at(j), at(j + 1), at(j + 2));
+                0.5 * (2.0 * p1
+                    + (p2 - p0) * t
+                    + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+                    + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t)
+            })
+            .collect()
+        }
+        Resampling::Spectral => {
+            let mut amplitudes = sine_transform(values);
+            amplitudes.truncate(size);
+            xs.map(|x| sine_series(&amplitudes, x)).collect()
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use super::display::DisplaySettings;
+use super::resample::Resampling;
+use super::string_dynamics::{check_finite, check_positive, round, Sys};
+use std::ops::RangeInclusive;
+
+/// Version written to new scenario files. Increase it when a change to the
+/// format needs old files to be converted on load.
+pub const SCENARIO_VERSION: u32 = 1;
+
+/// Time-steps that can be chosen in the app and loaded from a scenario.
+pub const DELTA_RANGE: RangeInclusive<f64> = 0.001..=0.75;
+
+/// Number of display colours: the boundary, the string and the masses.
+const DISPLAY_COLORS: usize = 3;
+
+/// A complete, reproducible experiment, stored as a RON file.
+///
+/// `system` holds the masses with their positions, velocities, inertias, pins
+/// and finger dampers, so it fixes the size, the initial shape and the
+/// boundary conditions, together with the spring model and its parameters,
+/// the hammer, the bow, the obstacles and the contact model. The string is
+/// integrated with position Verlet at a time-step of `delta`, starting from
+/// `time`. With `clamped` set, runs stop at `max_time`.
+///
+/// Missing fields take their default values, so a scenario only needs to list
+/// what differs from the defaults; `version` is required.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    /// A missing version reads as 0, which is rejected on load.
+    #[serde(default)]
+    pub version: u32,
+    pub description: String,
+    pub time: f64,
+    pub delta: f64,
+    pub clamped: bool,
+    pub max_time: f64,
+    pub resampling: Resampling,
+    pub system: Sys,
+    pub display: DisplaySettings,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            version: SCENARIO_VERSION,
+            description: String::new(),
+            time: 0.0,
+            delta: 0.1,
+            clamped: false,
+            max_time: 100.0,
+            resampling: Resampling::Linear,
+            system: Sys::new(0, 10, 1.0),
+            display: Default::default(),
+        }
+    }
+}
+
+impl Scenario {
+    pub fn from_ron(text: &str) -> Result<Scenario, String> {
+        let scenario: Scenario = ron::from_str(text).map_err(|e| e.to_string())?;
+        match scenario.version {
+            0 => Err("the scenario has no version".to_string()),
+            v if v > SCENARIO_VERSION => Err(format!(
+                "the scenario has version {}, but this build reads up to version {}",
+                v, SCENARIO_VERSION
+            )),
+            _ => scenario.validate().map(|()| scenario),
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        let config = ron::ser::PrettyConfig::new().indentor("  ".to_string());
+        ron::ser::to_string_pretty(self, config).map_err(|e| e.to_string())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.system.validate()?;
+        check_finite("the time", self.time)?;
+        check_finite("the max time", self.max_time)?;
+        if !DELTA_RANGE.contains(&self.delta) {
+            return Err(format!(
+                "the time-step {} is outside {} to {}",
+                self.delta,
+                DELTA_RANGE.start(),
+                DELTA_RANGE.end()
+            ));
+        }
+        if self.display.display_colors.len() < DISPLAY_COLORS {
+            return Err(format!(
+                "the display settings have {} colours instead of {}",
+                self.display.display_colors.len(),
+                DISPLAY_COLORS
+            ));
+        }
+        check_positive("the display width", self.display.width)?;
+        check_finite("the mass radius", self.display.radius as f64)?;
+        Ok(())
+    }
+
+    /// Time at which runs stop, if the scenario has a stop condition.
+    pub fn stop_time(&self) -> Option<f64> {
+        self.clamped.then(|| self.max_time)
+    }
+
+    /// Integrates until `until`, as the animation does.
+    pub fn run(&mut self, until: f64) {
+        while round(self.time, self.delta) < until {
+            self.system.update_system(&mut self.time, self.delta);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::obstacle::Obstacle;
+    use super::*;
+
+    fn plucked() -> Scenario {
+        let mut system = Sys::new(0, 12, 0.0);
+        system.pluck(1.0, 0.3);
+        let mut scenario = Scenario {
+            description: "plucked".to_string(),
+            clamped: true,
+            max_time: 3.0,
+            system,
+            ..Default::default()
+        };
+        scenario.run(1.0);
+        scenario
+    }
+
+    #[test]
+    fn round_trip() {
+        let scenario = plucked();
+        let text = scenario.to_ron().unwrap();
+        assert_eq!(Scenario::from_ron(&text).unwrap(), scenario);
+    }
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let scenario = Scenario::from_ron("(version: 1, max_time: 5)").unwrap();
+        assert_eq!(
+            scenario,
+            Scenario {
+                max_time: 5.0,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn versions_are_checked() {
+        let text = plucked().to_ron().unwrap();
+        let versioned = |version: &str| text.replacen("version: 1", version, 1);
+        assert!(Scenario::from_ron(&versioned("")).is_err());
+        assert!(Scenario::from_ron(&versioned("version: 0")).is_err());
+        assert!(Scenario::from_ron(&versioned("version: 2")).is_err());
+        assert!(Scenario::from_ron(&versioned("version: 1")).is_ok());
+    }
+
+    #[test]
+    fn invalid_scenarios_are_rejected() {
+        assert!(Scenario::from_ron("(version: 1, system: (masses: []))").is_err());
+        let mut scenario = plucked();
+        scenario.system.set_inertia(4, 0.0);
+        assert!(Scenario::from_ron(&scenario.to_ron().unwrap()).is_err());
+        scenario.system.set_inertia(4, -1.0);
+        assert!(Scenario::from_ron(&scenario.to_ron().unwrap()).is_err());
+        for delta in [0.0, -0.1, 0.0005, 0.8] {
+            let scenario = Scenario { delta, ..plucked() };
+            assert!(
+                Scenario::from_ron(&scenario.to_ron().unwrap()).is_err(),
+                "{}",
+                delta
+            );
+        }
+        let mut scenario = plucked();
+        scenario.display.display_colors.truncate(2);
+        assert!(Scenario::from_ron(&scenario.to_ron().unwrap()).is_err());
+    }
+
+    #[test]
+    fn non_positive_and_non_finite_parameters_are_rejected() {
+        type Edit = fn(&mut Scenario);
+        let edits: Vec<(&str, Edit)> = vec![
+            ("hammer mass", |s| s.system.hammer.mass = 0.0),
+            ("felt stiffness", |s| s.system.hammer.felt_stiffness = -1.0),
+            ("slip velocity", |s| s.system.bow.slip_velocity = 0.0),
+            ("position", |s| s.system.alter(3, f64::NAN)),
+            ("pin", |s| s.system.pin(3, f64::INFINITY)),
+            ("finger", |s| s.system.set_finger(3, f64::NAN)),
+            ("stiffness", |s| s.system.stiffness = f64::INFINITY),
+            ("gravity", |s| s.system.gravity = f64::NAN),
+            ("bow force", |s| s.system.bow.force = f64::NAN),
+            ("obstacle", |s| {
+                s.system.obstacles.push(Obstacle {
+                    height: f64::NEG_INFINITY,
+                    ..Default::default()
+                })
+            }),
+            ("time", |s| s.time = f64::NAN),
+            ("width", |s| s.display.width = 0.0),
+        ];
+        for (what, edit) in edits {
+            let mut scenario = plucked();
+            edit(&mut scenario);
+            let text = scenario.to_ron().unwrap();
+            assert!(Scenario::from_ron(&text).is_err(), "{}", what);
+        }
+        // Hand-written files that would leave the hammer stuck in flight or
+        // turn the bowed string into NaN.
+        for system in [
+            "(masses: [(pos: 0.5)], hammer: (mass: 0.0))",
+            "(masses: [(pos: 0.5)], bow: (enabled: true, slip_velocity: 0.0))",
+        ] {
+            let text = format!("(version: 1, system: {})", system);
+            assert!(Scenario::from_ron(&text).is_err(), "{}", system);
+        }
+    }
+}
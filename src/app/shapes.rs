@@ -0,0 +1,147 @@
+use super::string_dynamics::{interpolate, square};
+use std::f64::consts::PI;
+
+/// Family of localized initial shapes.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum PulseKind {
+    Pluck,
+    Gaussian,
+    Square,
+    WavePacket,
+}
+
+impl std::fmt::Display for PulseKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PulseKind::Pluck => write!(f, "Pluck"),
+            PulseKind::Gaussian => write!(f, "Gaussian"),
+            PulseKind::Square => write!(f, "Square"),
+            PulseKind::WavePacket => write!(f, "Wave packet"),
+        }
+    }
+}
+
+/// Whether a shape starts at rest or as a travelling wave.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Travel {
+    Standing,
+    Right,
+    Left,
+}
+
+impl std::fmt::Display for Travel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Travel::Standing => write!(f, "At rest"),
+            Travel::Right => write!(f, "Right-travelling"),
+            Travel::Left => write!(f, "Left-travelling"),
+        }
+    }
+}
+
+/// A pulse centred at `centre` with the given `width`, both fractions of the
+/// string length. `wavelength` is only used by wave packets.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pulse {
+    pub kind: PulseKind,
+    pub travel: Travel,
+    pub centre: f64,
+    pub width: f64,
+    pub wavelength: f64,
+}
+
+impl Default for Pulse {
+    fn default() -> Self {
+        Self {
+            kind: PulseKind::Gaussian,
+            travel: Travel::Standing,
+            centre: 0.5,
+            width: 0.1,
+            wavelength: 0.05,
+        }
+    }
+}
+
+impl Pulse {
+    /// Unit-height value of the pulse at `x`, a fraction of the string length.
+    pub fn value(&self, x: f64) -> f64 {
+        match self.kind {
+            PulseKind::Pluck => pluck(x, self.centre),
+            PulseKind::Gaussian => gaussian(x, self.centre, self.width),
+            PulseKind::Square => {
+                if (x - self.centre).abs() <= 0.5 * self.width {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            PulseKind::WavePacket => {
+                gaussian(x, self.centre, self.width)
+                    * (2.0 * PI * (x - self.centre) / self.wavelength).cos()
+            }
+        }
+    }
+
+    /// The pulse sampled at the positions of `n` masses.
+    pub fn shape(&self, n: usize, height: f64) -> Vec<f64> {
+        (0..n)
+            .map(|i| height * self.value((i as f64 + 1.0) / ((n + 1) as f64)))
+            .collect()
+    }
+}
+
+/// Triangle reaching 1 at `position` and 0 at both boundaries.
+pub fn pluck(x: f64, position: f64) -> f64 {
+    if x <= position {
+        x / position
+    } else {
+        (1.0 - x) / (1.0 - position)
+    }
+}
+
+pub fn gaussian(x: f64, centre: f64, width: f64) -> f64 {
+    (-square(x - centre) / (2.0 * square(width))).exp()
+}
+
+/// Shape of `n` masses following a freehand stroke through `points`, given as
+/// (fraction of length, displacement) pairs in any order. The curve is pinned
+/// to zero at both boundaries, and points off the string are ignored. A stroke
+/// with fewer than two points on the string has no shape.
+pub fn stroke(points: &[[f64; 2]], n: usize) -> Option<Vec<f64>> {
+    let mut points: Vec<[f64; 2]> = points
+        .iter()
+        .copied()
+        .filter(|p| p[0] > 0.0 && p[0] < 1.0)
+        .collect();
+    if points.len() < 2 {
+        return None;
+    }
+    points.sort_by(|a, b| a[0].partial_cmp(&b[0]).unwrap_or(std::cmp::Ordering::Equal));
+    points.insert(0, [0.0, 0.0]);
+    points.push([1.0, 0.0]);
+    Some(
+        (0..n)
+            .map(|i| interpolate(&points, (i as f64 + 1.0) / ((n + 1) as f64)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strokes_are_sampled_onto_the_masses() {
+        // Drawn right to left, and partly off the string.
+        let points = [[1.5, 2.0], [0.75, 1.0], [0.25, 1.0], [-0.5, 3.0]];
+        let shape = stroke(&points, 7).unwrap();
+        let expected = [0.5, 1.0, 1.0, 1.0, 1.0, 1.0, 0.5];
+        for (a, b) in shape.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-12, "{:?}", shape);
+        }
+        assert_eq!(stroke(&[[0.5, 1.0]], 7), None);
+        assert_eq!(stroke(&[[1.5, 1.0], [2.0, 1.0]], 7), None);
+    }
+}
@@ -1,12 +1,24 @@
+use super::bow::Bow;
+use super::hammer::Hammer;
+use super::obstacle::{ContactModel, Obstacle};
+use super::resample::{resample, Resampling};
+use super::shapes::{self, Travel};
 use std::f64::consts::PI;
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct Mass {
     pos: f64,
     past_pos: f64,
     accel: f64,
+    long_pos: f64,
+    past_long_pos: f64,
+    long_accel: f64,
+    pinned: Option<f64>,
+    finger: f64,
+    vel: f64,
+    inertia: f64,
 }
 impl Default for Mass {
     fn default() -> Self {
@@ -14,6 +26,13 @@ impl Default for Mass {
             pos: 0.0,
             past_pos: 0.0,
             accel: 0.0,
+            long_pos: 0.0,
+            past_long_pos: 0.0,
+            long_accel: 0.0,
+            pinned: None,
+            finger: 0.0,
+            vel: 0.0,
+            inertia: 1.0,
         }
     }
 }
@@ -22,79 +41,376 @@ impl Mass {
         Mass {
             pos: p,
             past_pos: p,
-            accel: 0.0,
+            ..Default::default()
         }
     }
 
     fn update_position(&mut self, t: f64, delta: f64) {
         if t == 0.0 {
             self.past_pos = self.pos;
+            self.past_long_pos = self.long_pos;
         } else if t == delta {
-            self.pos = self.past_pos + 0.5 * self.accel * square(delta);
+            self.pos = self.past_pos + self.vel * delta + 0.5 * self.accel * square(delta);
+            self.long_pos = self.past_long_pos + 0.5 * self.long_accel * square(delta);
         } else {
-            let cur = self.pos;
-            self.pos = 2.0 * cur - self.past_pos + self.accel * square(delta);
-            self.past_pos = cur;
+            self.verlet(delta);
+        }
+        self.hold_pin();
+    }
+
+    fn verlet(&mut self, delta: f64) {
+        let cur = self.pos;
+        self.pos = 2.0 * cur - self.past_pos + self.accel * square(delta);
+        self.past_pos = cur;
+        let cur = self.long_pos;
+        self.long_pos = 2.0 * cur - self.past_long_pos + self.long_accel * square(delta);
+        self.past_long_pos = cur;
+    }
+
+    fn hold_pin(&mut self) {
+        if let Some(p) = self.pinned {
+            self.pos = p;
+            self.past_pos = p;
         }
     }
 
-    fn update_acceleration(&mut self, l_pos: f64, r_pos: f64) {
-        self.accel = l_pos - 2.0 * self.pos + r_pos;
+    /// Exchanges the current and previous positions, which reverses the
+    /// direction of time for the Verlet update.
+    fn swap_past(&mut self) {
+        std::mem::swap(&mut self.pos, &mut self.past_pos);
+        std::mem::swap(&mut self.long_pos, &mut self.past_long_pos);
+    }
+
+    fn velocity(&self, delta: f64) -> f64 {
+        (self.pos - self.past_pos) / delta
+    }
+
+    fn update_acceleration(&mut self, l_pos: f64, r_pos: f64, nonlinearity: f64) {
+        let cubic = (r_pos - self.pos).powi(3) - (self.pos - l_pos).powi(3);
+        self.accel = (l_pos - 2.0 * self.pos + r_pos + nonlinearity * cubic) / self.inertia;
+        // The transverse model has no longitudinal degree of freedom and no force
+        // acting along the string. Displacements left over from the exact model
+        // would stay frozen, and the plots and exports would keep drawing the
+        // masses off their rest positions, so switching models drops them.
+        self.long_pos = 0.0;
+        self.past_long_pos = 0.0;
+        self.long_accel = 0.0;
+    }
+}
+
+/// How the springs between neighbouring masses are modelled.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum SpringModel {
+    /// Small-amplitude transverse motion only, with unit tension.
+    Transverse,
+    /// Each mass moves in (x, y) and every spring pulls along its own axis
+    /// with force `stiffness * (length - rest_length)`.
+    Exact,
+}
+
+impl std::fmt::Display for SpringModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpringModel::Transverse => write!(f, "Transverse"),
+            SpringModel::Exact => write!(f, "Exact 2D"),
+        }
+    }
+}
+
+/// One normal mode of a superposition, `amplitude * cos(omega * t + phase)`.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mode {
+    pub number: usize,
+    pub amplitude: f64,
+    pub phase: f64,
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            amplitude: 1.0,
+            phase: 0.0,
+        }
     }
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Sys {
     masses: Vec<Mass>,
+    pub spring_model: SpringModel,
+    /// Cubic term of the transverse spring force, `d + nonlinearity * d^3` for
+    /// a stretch `d`, as in the beta model of Fermi, Pasta, Ulam and Tsingou.
+    pub nonlinearity: f64,
+    pub stiffness: f64,
+    pub rest_length: f64,
+    pub hammer: Hammer,
+    pub bow: Bow,
+    pub obstacles: Vec<Obstacle>,
+    pub contact_model: ContactModel,
+    pub contact_stiffness: f64,
+    /// Uniform downward acceleration of every mass.
+    pub gravity: f64,
 }
 
 impl Sys {
     pub fn new(m: usize, size: usize, displacement: f64) -> Sys {
         let mut new_system = Sys {
             masses: vec![Mass::new(0.0); size],
+            ..Default::default()
         };
         new_system.masses[m].pos = displacement;
         new_system
     }
 
-    pub fn push(&mut self, displacement: f64) {
-        self.masses.push(Mass::new(displacement));
+    /// Same as `Sys::new` but keeps the physical parameters of the current system.
+    pub fn reset(&mut self, m: usize, size: usize, displacement: f64) {
+        self.masses = vec![Mass::new(0.0); size];
+        self.masses[m].pos = displacement;
+        self.hammer.stop();
     }
 
-    pub fn pop(&mut self) {
-        self.masses.pop();
+    /// Launches the hammer at the mass closest to its strike position.
+    pub fn strike(&mut self) {
+        let target = self.hammer.target(self.masses.len());
+        self.hammer.strike(self.masses[target].pos);
+    }
+
+    /// Changes the number of masses while keeping the shape and velocity of
+    /// the string. Pins and finger dampers are dropped, and every mass gets
+    /// unit inertia again, since a defect has no sensible interpolation.
+    pub fn resample(&mut self, size: usize, method: Resampling) {
+        let field = |get: fn(&Mass) -> f64| {
+            let values: Vec<f64> = self.masses.iter().map(get).collect();
+            resample(&values, size, method)
+        };
+        let pos = field(|m| m.pos);
+        let past_pos = field(|m| m.past_pos);
+        let accel = field(|m| m.accel);
+        let vel = field(|m| m.vel);
+        // Longitudinal displacements are measured in units of the spacing,
+        // which changes with the number of masses.
+        let scale = (size + 1) as f64 / (self.masses.len() + 1) as f64;
+        let long_pos = field(|m| m.long_pos);
+        let past_long_pos = field(|m| m.past_long_pos);
+        let long_accel = field(|m| m.long_accel);
+        self.masses = (0..size)
+            .map(|i| Mass {
+                pos: pos[i],
+                past_pos: past_pos[i],
+                accel: accel[i],
+                vel: vel[i],
+                long_pos: long_pos[i] * scale,
+                past_long_pos: past_long_pos[i] * scale,
+                long_accel: long_accel[i] * scale,
+                ..Default::default()
+            })
+            .collect();
     }
 
     pub fn update_system(&mut self, time_step: &mut f64, delta: f64) {
         for i in 0..self.masses.len() {
             self.masses[i].update_position(*time_step, delta);
         }
+        self.hammer.update_position(delta);
+        if self.contact_model == ContactModel::Exact {
+            for i in 0..self.masses.len() {
+                if let Some(floor) = self.obstacle_floor(i) {
+                    if self.masses[i].pos < floor {
+                        self.masses[i].pos = floor;
+                        self.masses[i].past_pos = floor;
+                    }
+                }
+            }
+        }
+        self.update_acceleration(delta);
+        let target = self.hammer.target(self.masses.len());
+        let force = self
+            .hammer
+            .contact(self.masses[target].pos, *time_step + delta, delta);
+        self.masses[target].accel += force / self.masses[target].inertia;
+        let target = self.bow.target(self.masses.len());
+        let force = self.bow.contact(
+            self.masses[target].velocity(delta),
+            self.masses[target].accel,
+            self.masses[target].inertia,
+            delta,
+        );
+        self.masses[target].accel += force / self.masses[target].inertia;
+        *time_step += delta;
+    }
+
+    /// Accelerations of the masses from the springs, finger dampers and
+    /// penalty contacts at their current positions.
+    fn update_acceleration(&mut self, delta: f64) {
+        match self.spring_model {
+            SpringModel::Transverse => self.update_transverse_acceleration(),
+            SpringModel::Exact => self.update_exact_acceleration(),
+        }
+        for mass in self.masses.iter_mut() {
+            mass.accel -= mass.finger * mass.velocity(delta) / mass.inertia + self.gravity;
+        }
+        if self.contact_model == ContactModel::Penalty {
+            for i in 0..self.masses.len() {
+                if let Some(floor) = self.obstacle_floor(i) {
+                    if self.masses[i].pos < floor {
+                        self.masses[i].accel += self.contact_stiffness
+                            * (floor - self.masses[i].pos)
+                            / self.masses[i].inertia;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Undoes one call to `update_system`, using the time reversibility of
+    /// position Verlet: with the current and previous positions swapped, a
+    /// forward update gives the position one step further in the past.
+    ///
+    /// Only the springs, gravity and penalty contacts are reversed exactly. Finger
+    /// dampers, the bow, the hammer and exact contacts lose energy or react to
+    /// events, so they are not undone.
+    pub fn step_back(&mut self, time_step: &mut f64, delta: f64) {
+        let steps = (*time_step / delta).round() as i64;
+        if steps <= 0 {
+            return;
+        }
+        if steps >= 3 {
+            for mass in self.masses.iter_mut() {
+                mass.swap_past();
+            }
+            self.update_acceleration(delta);
+            for mass in self.masses.iter_mut() {
+                mass.verlet(delta);
+                mass.hold_pin();
+                mass.swap_past();
+            }
+        } else if steps == 2 {
+            // After the first update the masses are still where they started,
+            // which is the previous position after the second.
+            for mass in self.masses.iter_mut() {
+                mass.pos = mass.past_pos;
+                mass.long_pos = mass.past_long_pos;
+                mass.hold_pin();
+            }
+        }
+        self.update_acceleration(delta);
+        // The first two updates are special cases that compare the time with
+        // 0 and delta exactly.
+        *time_step = if steps <= 2 {
+            (steps - 1) as f64 * delta
+        } else {
+            *time_step - delta
+        };
+    }
+
+    /// Largest displacement error after running a copy of the system forward
+    /// by `steps` steps and back again.
+    pub fn reversal_error(&self, time: f64, delta: f64, steps: usize) -> f64 {
+        let mut system = self.clone();
+        let mut t = time;
+        for _ in 0..steps {
+            system.update_system(&mut t, delta);
+        }
+        for _ in 0..steps {
+            system.step_back(&mut t, delta);
+        }
+        self.masses
+            .iter()
+            .zip(&system.masses)
+            .map(|(a, b)| (a.pos - b.pos).abs().max((a.long_pos - b.long_pos).abs()))
+            .fold(0.0, f64::max)
+    }
+
+    /// Highest obstacle under a mass, if any obstacle lies under it.
+    fn obstacle_floor(&self, i: usize) -> Option<f64> {
+        let spacing = 1.0 / (self.masses.len() + 1) as f64;
+        let x = (i as f64 + 1.0 + self.masses[i].long_pos) * spacing;
+        self.obstacles
+            .iter()
+            .filter_map(|obstacle| obstacle.profile(x, spacing))
+            .reduce(f64::max)
+    }
+
+    fn update_transverse_acceleration(&mut self) {
+        let beta = self.nonlinearity;
         for i in 0..self.masses.len() {
             if i == 0 {
                 if self.masses.len() > 1 {
                     let r = self.masses[i + 1].pos;
-                    self.masses[i].update_acceleration(0.0, r);
+                    self.masses[i].update_acceleration(0.0, r, beta);
                 } else {
-                    self.masses[i].update_acceleration(0.0, 0.0);
+                    self.masses[i].update_acceleration(0.0, 0.0, beta);
                 }
             } else if i == self.masses.len() - 1 {
                 let l = self.masses[i - 1].pos;
-                self.masses[i].update_acceleration(l, 0.0);
+                self.masses[i].update_acceleration(l, 0.0, beta);
             } else {
                 let r = self.masses[i + 1].pos;
                 let l = self.masses[i - 1].pos;
-                self.masses[i].update_acceleration(l, r);
+                self.masses[i].update_acceleration(l, r, beta);
             }
         }
-        *time_step += delta;
+    }
+
+    fn update_exact_acceleration(&mut self) {
+        let n = self.masses.len();
+        // Absolute coordinates, boundaries included, with unit equilibrium spacing.
+        let points: Vec<(f64, f64)> = (0..n + 2)
+            .map(|i| {
+                if i == 0 || i == n + 1 {
+                    (i as f64, 0.0)
+                } else {
+                    let mass = &self.masses[i - 1];
+                    (i as f64 + mass.long_pos, mass.pos)
+                }
+            })
+            .collect();
+        for i in 0..n {
+            let l = self.spring_force(points[i + 1], points[i]);
+            let r = self.spring_force(points[i + 1], points[i + 2]);
+            let inertia = self.masses[i].inertia;
+            self.masses[i].long_accel = (l.0 + r.0) / inertia;
+            self.masses[i].accel = (l.1 + r.1) / inertia;
+        }
+    }
+
+    /// Force exerted on a mass at `from` by a spring attached to `to`.
+    fn spring_force(&self, from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length = f64::sqrt(square(dx) + square(dy));
+        if length == 0.0 {
+            return (0.0, 0.0);
+        }
+        let f = self.stiffness * (length - self.rest_length) / length;
+        (f * dx, f * dy)
+    }
+
+    pub fn positions(&self) -> Vec<f64> {
+        self.masses.iter().map(|m| m.pos).collect()
     }
 
     pub fn get_mass_pos(&self, mass: usize) -> f64 {
         self.masses[mass].pos
     }
 
+    pub fn get_mass_vel(&self, mass: usize, delta: f64) -> f64 {
+        self.masses[mass].velocity(delta)
+    }
+
+    pub fn get_mass_accel(&self, mass: usize) -> f64 {
+        self.masses[mass].accel
+    }
+
+    /// Longitudinal displacement of a mass from its equilibrium spacing.
+    pub fn get_mass_long_pos(&self, mass: usize) -> f64 {
+        self.masses[mass].long_pos
+    }
+
     pub fn len(&self) -> usize {
         self.masses.len()
     }
@@ -116,24 +432,219 @@ impl Sys {
         }
     }
 
-    pub fn pluck(&mut self, height: f64) {
+    /// Tension of the string at rest.
+    pub fn tension(&self) -> f64 {
+        match self.spring_model {
+            SpringModel::Transverse => 1.0,
+            SpringModel::Exact => self.stiffness * (1.0 - self.rest_length),
+        }
+    }
+
+    /// Angular frequency of transverse mode `k` of the discrete chain.
+    pub fn eigenfrequency(&self, k: usize) -> f64 {
+        let n = self.masses.len() as f64;
+        2.0 * self.tension().max(0.0).sqrt() * (k as f64 * PI / (2.0 * (n + 1.0))).sin()
+    }
+
+    /// Loads a superposition of normal modes, with each phase splitting the
+    /// mode between the initial displacement and the initial velocity.
+    pub fn superposition(&mut self, modes: &[Mode]) {
+        let n = self.masses.len();
+        let mut shape = vec![0.0; n];
+        let mut velocity = vec![0.0; n];
+        for mode in modes {
+            let omega = self.eigenfrequency(mode.number);
+            for i in 0..n {
+                let profile = ((i + 1) as f64 / (n + 1) as f64 * PI * mode.number as f64).sin();
+                shape[i] += mode.amplitude * mode.phase.cos() * profile;
+                velocity[i] -= mode.amplitude * omega * mode.phase.sin() * profile;
+            }
+        }
+        self.set_state(&shape, &velocity);
+    }
+
+    pub fn pluck(&mut self, height: f64, position: f64) {
         let n = self.masses.len();
         for i in 0..n {
-            let pos = -2.0 * f64::abs(((i + 1) as f64 / (n as f64 + 1.0)) - 0.5) + 1.0;
+            let pos = shapes::pluck((i + 1) as f64 / (n as f64 + 1.0), position);
 
             self.masses[i] = Mass::new(pos * height);
         }
     }
 
+    /// Loads `shape`, either at rest or with the velocity that makes it travel
+    /// as a single wave in the given direction.
+    pub fn launch(&mut self, shape: &[f64], travel: Travel) {
+        let direction = match travel {
+            Travel::Standing => 0.0,
+            Travel::Right => 1.0,
+            Travel::Left => -1.0,
+        };
+        // Wave speed in masses per unit time, for unit mass and spacing.
+        let speed = self.tension().max(0.0).sqrt();
+        let n = shape.len();
+        let at = |i: isize| {
+            if i < 0 || i >= n as isize {
+                0.0
+            } else {
+                shape[i as usize]
+            }
+        };
+        let velocity: Vec<f64> = (0..n as isize)
+            .map(|i| -direction * speed * 0.5 * (at(i + 1) - at(i - 1)))
+            .collect();
+        self.set_state(shape, &velocity);
+    }
+
+    pub fn longitudinal_state(&mut self, height: f64, state: i32) {
+        let spacing = (self.masses.len() + 1) as f64;
+        for i in 0..self.masses.len() {
+            let pos = ((i + 1) as f64 / (spacing) * PI * (state as f64)).sin();
+            self.masses[i] = Mass::new(0.0);
+            self.masses[i].long_pos = pos * height;
+            self.masses[i].past_long_pos = pos * height;
+        }
+    }
+
+    /// Replaces every displacement with `shape`, starting from rest.
+    pub fn set_shape(&mut self, shape: &[f64]) {
+        for (mass, &pos) in self.masses.iter_mut().zip(shape) {
+            *mass = Mass::new(pos);
+        }
+    }
+
+    /// Replaces every displacement with `shape` and launches the masses with
+    /// `velocity` on the first step.
+    pub fn set_state(&mut self, shape: &[f64], velocity: &[f64]) {
+        self.set_shape(shape);
+        for (mass, &vel) in self.masses.iter_mut().zip(velocity) {
+            mass.vel = vel;
+        }
+    }
+
+    /// Inserts a mass before index `i`, halfway between its neighbours.
+    pub fn insert(&mut self, i: usize, inertia: f64) {
+        let neighbour = |j: Option<usize>, get: fn(&Mass) -> f64| {
+            j.and_then(|j| self.masses.get(j)).map_or(0.0, get)
+        };
+        let before = i.checked_sub(1);
+        let mass = Mass {
+            pos: 0.5 * (neighbour(before, |m| m.pos) + neighbour(Some(i), |m| m.pos)),
+            past_pos: 0.5
+                * (neighbour(before, |m| m.past_pos) + neighbour(Some(i), |m| m.past_pos)),
+            inertia,
+            ..Default::default()
+        };
+        self.masses.insert(i, mass);
+    }
+
+    pub fn remove(&mut self, i: usize) {
+        self.masses.remove(i);
+    }
+
+    pub fn get_inertia(&self, i: usize) -> f64 {
+        self.masses[i].inertia
+    }
+
+    pub fn set_inertia(&mut self, i: usize, inertia: f64) {
+        self.masses[i].inertia = inertia;
+    }
+
     pub fn alter(&mut self, i: usize, displacement: f64) {
         self.masses[i].pos = displacement;
+        if self.masses[i].pinned.is_some() {
+            self.pin(i, displacement);
+        }
+    }
+
+    /// Holds the transverse displacement of a mass fixed until it is released.
+    pub fn pin(&mut self, i: usize, displacement: f64) {
+        let mass = &mut self.masses[i];
+        mass.pinned = Some(displacement);
+        mass.pos = displacement;
+        mass.past_pos = displacement;
+    }
+
+    pub fn release(&mut self, i: usize) {
+        self.masses[i].pinned = None;
+    }
+
+    pub fn is_pinned(&self, i: usize) -> bool {
+        self.masses[i].pinned.is_some()
+    }
+
+    /// Damping coefficient of a finger lightly touching the mass.
+    pub fn get_finger(&self, i: usize) -> f64 {
+        self.masses[i].finger
+    }
+
+    pub fn set_finger(&mut self, i: usize, damping: f64) {
+        self.masses[i].finger = damping;
+    }
+
+    pub fn alter_long(&mut self, i: usize, displacement: f64) {
+        self.masses[i].long_pos = displacement;
+    }
+
+    /// Checks that every state and parameter is finite and that inertias and
+    /// other quantities that are divided by are positive, as they are in any
+    /// system built through the app. Loaded files can hold anything.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.masses.is_empty() {
+            return Err("the string has no masses".to_string());
+        }
+        for (i, mass) in self.masses.iter().enumerate() {
+            let name = |field: &str| format!("{} of mass {}", field, i);
+            check_positive(&name("inertia"), mass.inertia)?;
+            for (field, value) in [
+                ("position", mass.pos),
+                ("previous position", mass.past_pos),
+                ("acceleration", mass.accel),
+                ("velocity", mass.vel),
+                ("longitudinal position", mass.long_pos),
+                ("previous longitudinal position", mass.past_long_pos),
+                ("longitudinal acceleration", mass.long_accel),
+                ("finger damping", mass.finger),
+                ("pin position", mass.pinned.unwrap_or(0.0)),
+            ] {
+                check_finite(&name(field), value)?;
+            }
+        }
+        for (what, value) in [
+            ("the nonlinearity", self.nonlinearity),
+            ("the stiffness", self.stiffness),
+            ("the rest length", self.rest_length),
+            ("the contact stiffness", self.contact_stiffness),
+            ("gravity", self.gravity),
+        ] {
+            check_finite(what, value)?;
+        }
+        self.hammer.validate()?;
+        self.bow.validate()?;
+        for (i, obstacle) in self.obstacles.iter().enumerate() {
+            obstacle
+                .validate()
+                .map_err(|e| format!("obstacle {}: {}", i, e))?;
+        }
+        Ok(())
     }
 }
 
 impl Default for Sys {
     fn default() -> Self {
+        // Stiffness and rest length give unit tension, matching the transverse model.
         Self {
             masses: vec![Mass::new(1.0)],
+            spring_model: SpringModel::Transverse,
+            nonlinearity: 0.0,
+            stiffness: 2.0,
+            rest_length: 0.5,
+            hammer: Default::default(),
+            bow: Default::default(),
+            obstacles: Vec::new(),
+            contact_model: ContactModel::Penalty,
+            contact_stiffness: 50.0,
+            gravity: 0.0,
         }
     }
 }
@@ -141,6 +652,242 @@ impl Default for Sys {
 pub fn square(val: f64) -> f64 {
     val * val
 }
+/// Fails with a message naming `what` unless `value` is finite.
+pub fn check_finite(what: &str, value: f64) -> Result<(), String> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(format!("{} is {}", what, value))
+    }
+}
+/// Fails with a message naming `what` unless `value` is positive and finite.
+pub fn check_positive(what: &str, value: f64) -> Result<(), String> {
+    if value > 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        Err(format!("{} is {}, which is not positive", what, value))
+    }
+}
+/// Index of the mass closest to `fraction` of the string length, for `n` masses.
+pub fn nearest_mass(fraction: f64, n: usize) -> usize {
+    let i = (fraction * (n + 1) as f64).round() as usize;
+    i.clamp(1, n) - 1
+}
+/// Linear interpolation through `points`, which must be sorted by x. Values
+/// outside the range are held at the nearest end point.
+pub fn interpolate(points: &[[f64; 2]], x: f64) -> f64 {
+    match points.iter().position(|p| p[0] >= x) {
+        None => points.last().map_or(0.0, |p| p[1]),
+        Some(0) => points[0][1],
+        Some(j) => {
+            let [x0, y0] = points[j - 1];
+            let [x1, y1] = points[j];
+            if x1 == x0 {
+                y1
+            } else {
+                y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+            }
+        }
+    }
+}
 pub fn round(val: f64, rounding_factor: f64) -> f64 {
     f64::floor(val / rounding_factor) * rounding_factor
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plucked(size: usize) -> Sys {
+        let mut system = Sys::new(0, size, 0.0);
+        system.pluck(1.0, 0.3);
+        system
+    }
+
+    #[test]
+    fn stepping_back_retraces_the_run() {
+        let delta = 0.1;
+        let start = plucked(20);
+        let mut system = start.clone();
+        let mut time = 0.0;
+        for _ in 0..1000 {
+            system.update_system(&mut time, delta);
+        }
+        for _ in 0..1000 {
+            system.step_back(&mut time, delta);
+        }
+        assert_eq!(time, 0.0);
+        let error = start
+            .positions()
+            .iter()
+            .zip(system.positions())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max);
+        assert!(error < 1e-10, "{}", error);
+        assert!(start.reversal_error(0.0, delta, 1000) < 1e-10);
+    }
+
+    #[test]
+    fn reversal_error_mid_run() {
+        let delta = 0.05;
+        for model in [SpringModel::Transverse, SpringModel::Exact] {
+            let mut system = plucked(15);
+            system.spring_model = model;
+            let mut time = 0.0;
+            for _ in 0..37 {
+                system.update_system(&mut time, delta);
+            }
+            let error = system.reversal_error(time, delta, 500);
+            assert!(error < 1e-10, "{}: {}", model, error);
+        }
+    }
+
+    #[test]
+    fn dissipation_is_not_reversed() {
+        let mut system = plucked(15);
+        for i in 0..system.len() {
+            system.set_finger(i, 0.2);
+        }
+        assert!(system.reversal_error(0.0, 0.1, 500) > 1e-6);
+    }
+
+    #[test]
+    fn exact_contact_holds_masses_at_the_fret() {
+        // 19 masses put mass 9 at the middle, where the fret sits.
+        let mut system = Sys::new(0, 19, 0.0);
+        system.pluck(-1.0, 0.5);
+        system.obstacles.push(Obstacle {
+            height: -0.2,
+            ..Default::default()
+        });
+        system.contact_model = ContactModel::Exact;
+        let mut time = 0.0;
+        let mut held = 0;
+        for _ in 0..2000 {
+            system.update_system(&mut time, 0.05);
+            let pos = system.get_mass_pos(9);
+            assert!(pos >= -0.2, "{} at time {}", pos, time);
+            if pos == -0.2 {
+                held += 1;
+            }
+        }
+        assert!(held > 0);
+    }
+
+    #[test]
+    fn pinned_masses_hold_until_released() {
+        let delta = 0.1;
+        let mut system = plucked(20);
+        let mut time = 0.0;
+        for _ in 0..50 {
+            system.update_system(&mut time, delta);
+        }
+        system.pin(5, 0.3);
+        for _ in 0..500 {
+            system.update_system(&mut time, delta);
+            assert_eq!(system.get_mass_pos(5), 0.3);
+            assert_eq!(system.get_mass_vel(5, delta), 0.0);
+        }
+        system.release(5);
+        assert!(!system.is_pinned(5));
+        for _ in 0..50 {
+            system.update_system(&mut time, delta);
+        }
+        assert!(system.get_mass_pos(5) != 0.3);
+    }
+
+    #[test]
+    fn dragging_moves_pins_with_the_mass() {
+        let delta = 0.1;
+        let mut system = Sys::new(0, 10, 0.0);
+        system.pin(4, 0.0);
+        system.alter(4, 0.5);
+        system.alter(7, -0.5);
+        let mut time = 0.0;
+        for _ in 0..100 {
+            system.update_system(&mut time, delta);
+        }
+        assert_eq!(system.get_mass_pos(4), 0.5);
+        assert!(system.get_mass_pos(7) != -0.5);
+    }
+
+    #[test]
+    fn travelling_pulses_move_at_unit_speed() {
+        let n = 199;
+        let pulse = shapes::Pulse {
+            centre: 0.5,
+            width: 0.03,
+            ..Default::default()
+        };
+        let shape = pulse.shape(n, 1.0);
+        let delta = 0.05;
+        for (travel, direction) in [(Travel::Right, 1.0), (Travel::Left, -1.0)] {
+            let mut system = Sys::new(0, n, 0.0);
+            system.launch(&shape, travel);
+            let mut time = 0.0;
+            while round(time, delta) < 50.0 {
+                system.update_system(&mut time, delta);
+            }
+            let positions = system.positions();
+            let (peak, height) = positions
+                .iter()
+                .copied()
+                .enumerate()
+                .fold((0, f64::MIN), |a, b| if b.1 > a.1 { b } else { a });
+            // The pulse starts on mass 99 and the wave speed is one mass per unit time.
+            let expected = 99.0 + direction * 50.0;
+            assert!((peak as f64 - expected).abs() <= 1.0, "{}: {}", travel, peak);
+            assert!(height > 0.97, "{}: {}", travel, height);
+            // Nothing is left travelling the other way.
+            let behind = positions
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| (*i as f64 - 99.0) * direction < 0.0)
+                .map(|(_, p)| p.abs())
+                .fold(0.0, f64::max);
+            assert!(behind < 0.03, "{}: {}", travel, behind);
+        }
+    }
+
+    #[test]
+    fn inserting_and_removing_masses() {
+        let start = plucked(10);
+        let mut system = start.clone();
+        system.insert(3, 4.0);
+        assert_eq!(system.len(), 11);
+        assert_eq!(system.get_inertia(3), 4.0);
+        let halfway = 0.5 * (start.get_mass_pos(2) + start.get_mass_pos(3));
+        assert_eq!(system.get_mass_pos(3), halfway);
+        system.insert(0, 1.0);
+        system.insert(system.len(), 1.0);
+        assert_eq!(system.get_mass_pos(0), 0.5 * start.get_mass_pos(0));
+        system.remove(system.len() - 1);
+        system.remove(0);
+        system.remove(3);
+        assert_eq!(system, start);
+    }
+
+    #[test]
+    fn heavy_defects_reflect_pulses() {
+        let n = 199;
+        let pulse = shapes::Pulse {
+            centre: 0.25,
+            width: 0.03,
+            ..Default::default()
+        };
+        let mut system = Sys::new(0, n, 0.0);
+        system.launch(&pulse.shape(n, 1.0), Travel::Right);
+        system.set_inertia(149, 1000.0);
+        let mut time = 0.0;
+        while round(time, 0.05) < 150.0 {
+            system.update_system(&mut time, 0.05);
+        }
+        // The pulse reaches the nearly fixed mass 149 at time 100 and comes back
+        // upside down.
+        let positions = system.positions();
+        let lowest = positions.iter().copied().fold(0.0, f64::min);
+        assert!(lowest < -0.9, "{}", lowest);
+        let transmitted = positions[150..].iter().map(|p| p.abs()).fold(0.0, f64::max);
+        assert!(transmitted < 0.1, "{}", transmitted);
+    }
+}
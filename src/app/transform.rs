@@ -0,0 +1,110 @@
+use std::f64::consts::PI;
+
+/// Discrete sine transform of values sampled at `x = (i + 1) / (n + 1)` on a
+/// string fixed at both ends. Entry `k - 1` is the amplitude of mode `k`.
+pub fn sine_transform(values: &[f64]) -> Vec<f64> {
+    (1..=values.len())
+        .map(|k| sine_coefficient(values, k))
+        .collect()
+}
+
+/// Amplitude of mode `k` alone, see `sine_transform`.
+pub fn sine_coefficient(values: &[f64], k: usize) -> f64 {
+    let n = values.len();
+    2.0 / (n + 1) as f64
+        * values
+            .iter()
+            .enumerate()
+            .map(|(i, y)| y * ((i + 1) as f64 * k as f64 * PI / (n + 1) as f64).sin())
+            .sum::<f64>()
+}
+
+/// Evaluates the sine series with the given mode amplitudes at `x`, a fraction
+/// of the string length.
+pub fn sine_series(amplitudes: &[f64], x: f64) -> f64 {
+    amplitudes
+        .iter()
+        .enumerate()
+        .map(|(k, b)| b * ((k + 1) as f64 * PI * x).sin())
+        .sum()
+}
+
+/// Indices of the `count` largest local maxima of `values`, largest first.
+/// Maxima smaller than `threshold` times the overall maximum are ignored.
+pub fn peaks(values: &[f64], count: usize, threshold: f64) -> Vec<usize> {
+    let largest = values.iter().copied().fold(0.0, f64::max);
+    let mut peaks: Vec<usize> = (0..values.len())
+        .filter(|&i| {
+            let left = i == 0 || values[i] > values[i - 1];
+            let right = i + 1 == values.len() || values[i] >= values[i + 1];
+            left && right && values[i] > threshold * largest
+        })
+        .collect();
+    peaks.sort_by(|&a, &b| {
+        values[b]
+            .partial_cmp(&values[a])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    peaks.truncate(count);
+    peaks
+}
+
+/// In-place radix-2 fast Fourier transform. The length must be a power of two.
+pub fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        let twiddles: Vec<(f64, f64)> = (0..len / 2)
+            .map(|k| ((angle * k as f64).cos(), (angle * k as f64).sin()))
+            .collect();
+        for start in (0..n).step_by(len) {
+            for (k, &(w_re, w_im)) in twiddles.iter().enumerate() {
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Hann window of length `n`.
+pub fn hann(n: usize) -> Vec<f64> {
+    (0..n)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / n as f64).cos())
+        .collect()
+}
+
+/// Magnitudes of the non-negative frequency bins of `samples`, after removing
+/// the mean, applying a Hann window and zero-padding to `size` (a power of two).
+pub fn magnitude_spectrum(samples: &[f64], size: usize) -> Vec<f64> {
+    let mean = samples.iter().sum::<f64>() / samples.len().max(1) as f64;
+    let window = hann(samples.len());
+    let mut re = vec![0.0; size];
+    let mut im = vec![0.0; size];
+    for (i, (y, w)) in samples.iter().zip(&window).enumerate().take(size) {
+        re[i] = (y - mean) * w;
+    }
+    fft(&mut re, &mut im);
+    (0..=size / 2)
+        .map(|k| (re[k] * re[k] + im[k] * im[k]).sqrt())
+        .collect()
+}
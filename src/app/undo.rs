@@ -0,0 +1,108 @@
+/// Undo and redo stacks of states of type `T`.
+///
+/// Call `begin_frame` before the user interface runs and `end_frame` after
+/// it: any change in between is an edit, and the state before it can be
+/// restored with `undo`. Changes in consecutive frames, or while the pointer
+/// stays down, form a single edit, so dragging a slider is undone at once.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+#[derive(Debug, Clone)]
+pub struct UndoStack<T> {
+    pub capacity: usize,
+    undo: Vec<T>,
+    redo: Vec<T>,
+    #[serde(skip)]
+    before: Option<T>,
+    #[serde(skip)]
+    editing: bool,
+}
+
+impl<T> Default for UndoStack<T> {
+    fn default() -> Self {
+        Self {
+            capacity: 100,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            before: None,
+            editing: false,
+        }
+    }
+}
+
+impl<T> UndoStack<T> {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn begin_frame(&mut self, state: T) {
+        self.before = Some(state);
+    }
+
+    /// The state passed to `begin_frame`, until the frame ends.
+    pub fn before(&self) -> Option<&T> {
+        self.before.as_ref()
+    }
+
+    /// Ends the frame. `changed` tells whether the state differs from `before`,
+    /// which lets callers compare it without making another copy.
+    pub fn end_frame(&mut self, changed: bool, pointer_down: bool) {
+        let before = match self.before.take() {
+            Some(before) => before,
+            None => return,
+        };
+        if changed && !self.editing {
+            if self.undo.len() >= self.capacity.max(1) {
+                self.undo.remove(0);
+            }
+            self.undo.push(before);
+            self.redo.clear();
+        }
+        self.editing = changed || (self.editing && pointer_down);
+    }
+
+    /// The state before the last edit, if any; `current` can be restored with `redo`.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let state = self.undo.pop()?;
+        self.redo.push(current);
+        self.editing = false;
+        Some(state)
+    }
+
+    /// The state undone last, if any; `current` can be restored with `undo`.
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let state = self.redo.pop()?;
+        self.undo.push(current);
+        self.editing = false;
+        Some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_are_grouped_and_persisted() {
+        let mut stack = UndoStack::default();
+        // A drag over two frames, then a separate edit.
+        for (before, changed, pointer_down) in [(0, true, true), (1, true, true), (2, true, false)] {
+            stack.begin_frame(before);
+            stack.end_frame(changed, pointer_down);
+        }
+        stack.begin_frame(3);
+        stack.end_frame(false, false);
+        stack.begin_frame(3);
+        stack.end_frame(true, false);
+
+        let text = ron::to_string(&stack).unwrap();
+        let mut stack: UndoStack<i32> = ron::from_str(&text).unwrap();
+        assert_eq!(stack.undo(4), Some(3));
+        assert_eq!(stack.undo(3), Some(0));
+        assert_eq!(stack.undo(0), None);
+        assert_eq!(stack.redo(0), Some(3));
+    }
+}
@@ -0,0 +1,22 @@
+#![warn(clippy::all, rust_2018_idioms)]
+
+// Runs scenario files without a window.
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match loaded_string::headless::run(&args) {
+        Ok(lines) => {
+            for line in lines {
+                println!("{}", line);
+            }
+        }
+        Err(error) => {
+            eprintln!("error: {}", error);
+            eprintln!("{}", loaded_string::headless::USAGE);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
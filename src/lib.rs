@@ -1,6 +1,7 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
+pub use app::headless;
 pub use app::SystemPlot;
 
 // ----------------------------------------------------------------------------